@@ -9,7 +9,7 @@ pub fn benchmark(criterion: &mut Criterion) {
     for size in (1..=16).map(|i| i * i * i * 8) {
         let bv = BitVec::new_with_random(size);
         group.bench_with_input(
-            BenchmarkId::new(format!("Size:"), &size),
+            BenchmarkId::new("Size:", size),
             &size,
             |bencher, _| {
                 bencher.iter(|| RankSupport::new(&bv));
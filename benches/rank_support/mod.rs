@@ -3,7 +3,3 @@ pub mod generation;
 pub mod overhead;
 pub mod rank;
 mod utilities;
-
-pub use dummy_rank::*;
-pub use generation::*;
-pub use rank::*;
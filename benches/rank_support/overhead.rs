@@ -1,8 +1,8 @@
 #[cfg(test)]
 mod benchmark_overhead {
-    use bvrs::{BitVec, RankSupport};
     #[test]
     pub fn benchmark() {
+        use bvrs::{BitVec, RankSupport};
         let mut overheads = vec![];
         for i in 1..=84 {
             let size = i * i * 8;
@@ -6,12 +6,12 @@ pub fn benchmark(criterion: &mut Criterion) {
     let mut group = criterion.benchmark_group("Get Rank1");
     group.plot_config(PlotConfiguration::default());
 
-    for size in (1..=16).map(|i| i * i * i * 8 as u64) {
+    for size in (1..=16).map(|i| i * i * i * 8_u64) {
         let bv = BitVec::new_with_random(size as usize);
         let r = RankSupport::new(&bv);
-        let positions = RankSupportUtilities::uniform_sample_range((0..size), 1000);
+        let positions = RankSupportUtilities::uniform_sample_range(0..size, 1000);
         group.bench_with_input(
-            BenchmarkId::new(format!("Size:"), &size),
+            BenchmarkId::new("Size:", size),
             &size,
             |bencher, _| {
                 bencher.iter(|| {
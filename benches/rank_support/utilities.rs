@@ -1,8 +1,5 @@
 use bvrs::{BitVec, RankSupport};
-use rand;
-use rand::distributions::{Distribution, Standard};
 use rand::Rng;
-use std::collections::HashSet;
 use std::ops::Range;
 
 pub struct RankSupportUtilities;
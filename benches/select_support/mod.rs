@@ -1,4 +1,2 @@
 pub mod select;
 mod utilities;
-
-pub use select::*;
@@ -1,6 +1,4 @@
-use bvrs::{BitVec, RankSupport, SelectSupport};
-use rand;
-use rand::distributions::{Distribution, Standard};
+use bvrs::SelectSupport;
 use rand::Rng;
 use std::ops::Range;
 
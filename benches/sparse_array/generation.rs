@@ -11,7 +11,7 @@ pub fn benchmark(criterion: &mut Criterion) {
             let elements: Vec<(usize, u64)> =
                 SparseArrayUtilities::create_sparse_array_elements(size, sparsity);
             group.bench_with_input(
-                BenchmarkId::new(format!("Sparsity={}", sparsity), &size),
+                BenchmarkId::new(format!("Sparsity={}", sparsity), size),
                 &size,
                 |bencher, _| {
                     bencher.iter(|| {
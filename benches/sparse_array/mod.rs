@@ -3,7 +3,3 @@ pub mod index;
 // pub mod overhead;
 pub mod rank;
 pub mod utilities;
-
-pub use generation::*;
-pub use rank::*;
-pub use utilities::SparseArrayUtilities;
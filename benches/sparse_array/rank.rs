@@ -1,5 +1,5 @@
+#![allow(dead_code)]
 use super::utilities::SparseArrayUtilities;
-use bvrs::SparseArray;
 use criterion::{criterion_group, BenchmarkId, Criterion, PlotConfiguration};
 
 pub fn benchmark(criterion: &mut Criterion) {
@@ -11,9 +11,9 @@ pub fn benchmark(criterion: &mut Criterion) {
             let elements: Vec<(usize, u64)> =
                 SparseArrayUtilities::create_sparse_array_elements(size, sparsity);
             let sa = SparseArrayUtilities::create_sparse_array(&elements);
-            let positions = SparseArrayUtilities::uniform_sample_range((0..size), 1000);
+            let positions = SparseArrayUtilities::uniform_sample_range(0..size, 1000);
             group.bench_with_input(
-                BenchmarkId::new(format!("Sparsity={}", sparsity), &size),
+                BenchmarkId::new(format!("Sparsity={}", sparsity), size),
                 &size,
                 |bencher, _| {
                     bencher.iter(|| {
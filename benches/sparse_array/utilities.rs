@@ -1,5 +1,5 @@
+#![allow(dead_code)]
 use bvrs::SparseArray;
-use rand;
 use rand::distributions::{Distribution, Standard};
 use rand::Rng;
 use std::collections::HashSet;
@@ -23,7 +23,7 @@ impl SparseArrayUtilities {
         for _ in 0..actual_size {
             positions_hashset.insert(rng.gen_range(0..size));
         }
-        let mut positions: Vec<usize> = positions_hashset.into_iter().map(|x| x).collect();
+        let mut positions: Vec<usize> = positions_hashset.into_iter().collect();
         positions.sort();
         let mut elements = vec![];
         for i in positions {
@@ -0,0 +1,440 @@
+//! Bit streams over [`BitVec`] with universal integer codes.
+//!
+//! A [`BitWriter`] appends fixed-width fields and variable-length codes to a
+//! growing bit vector, and a [`BitReader`] decodes them back in the same order.
+//! Fixed-width fields are written most significant bit first, like the rest
+//! of the crate.
+
+use crate::bit_vec::{BitVec, WORD_SIZE};
+use crate::int_vec::bit_width;
+
+/// Fibonacci numbers `1, 2, 3, 5, ...` up to the largest below `2^64`.
+const FIBONACCI: [u64; 92] = {
+    let mut fib = [0; 92];
+    fib[0] = 1;
+    fib[1] = 2;
+    let mut i = 2;
+    while i < fib.len() {
+        fib[i] = fib[i - 1] + fib[i - 2];
+        i += 1;
+    }
+    fib
+};
+
+/// Bits per group of a varint, each group being preceded by a continuation bit.
+const VARINT_GROUP: usize = 7;
+
+/// Appends bit fields and codes to a [`BitVec`].
+#[derive(Debug, Clone)]
+pub struct BitWriter {
+    bits: BitVec,
+}
+
+impl Default for BitWriter {
+    fn default() -> Self {
+        BitWriter::new()
+    }
+}
+
+impl BitWriter {
+    /// Makes room for `width` more bits, returning where they start.
+    fn grow(&mut self, width: usize) -> usize {
+        let pos = self.bits.len();
+        self.bits.resize(pos + width, false);
+        pos
+    }
+}
+
+impl BitWriter /* Public API */ {
+    pub fn new() -> BitWriter {
+        BitWriter {
+            bits: BitVec::new(0),
+        }
+    }
+    /// Number of bits written so far.
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn write_bit(&mut self, bit: bool) {
+        self.bits.push(bit);
+    }
+    /// Writes the low `width <= 64` bits of `value`.
+    pub fn write_bits(&mut self, value: u64, width: usize) {
+        assert!(width <= WORD_SIZE, "fields are at most 64 bits wide");
+        let pos = self.grow(width);
+        self.bits.set_bits(pos, width, value);
+    }
+    /// `n` zeros followed by a one.
+    pub fn write_unary(&mut self, n: u64) {
+        let pos = self.grow(n as usize + 1);
+        self.bits.set(pos + n as usize);
+    }
+    /// Elias gamma code of `x >= 1`: the bit width of `x` minus one in unary,
+    /// then `x` without its leading one.
+    pub fn write_gamma(&mut self, x: u64) {
+        assert!(x > 0, "gamma codes start at 1");
+        let width = bit_width(x);
+        self.write_unary(width as u64 - 1);
+        self.write_bits(x, width - 1);
+    }
+    /// Elias delta code of `x >= 1`: the bit width of `x` in gamma code, then
+    /// `x` without its leading one.
+    pub fn write_delta(&mut self, x: u64) {
+        assert!(x > 0, "delta codes start at 1");
+        let width = bit_width(x);
+        self.write_gamma(width as u64);
+        self.write_bits(x, width - 1);
+    }
+    /// Golomb code of `x` with modulus `m >= 1`: the quotient in unary, then
+    /// the remainder in truncated binary.
+    pub fn write_golomb(&mut self, x: u64, m: u64) {
+        assert!(m > 0, "Golomb modulus must be positive");
+        self.write_unary(x / m);
+        let r = x % m;
+        let b = bit_width(m - 1);
+        let cutoff = ((1_u128 << b) - m as u128) as u64;
+        if r < cutoff {
+            self.write_bits(r, b - 1);
+        } else {
+            self.write_bits(r + cutoff, b);
+        }
+    }
+    /// Golomb-Rice code of `x` with modulus `2^k`, `k <= 64`.
+    pub fn write_rice(&mut self, x: u64, k: usize) {
+        assert!(k <= WORD_SIZE, "Rice parameters are at most 64");
+        self.write_unary(x.checked_shr(k as u32).unwrap_or(0));
+        self.write_bits(x, k);
+    }
+    /// Fibonacci code of `x >= 1`: its Zeckendorf representation, smallest
+    /// term first, closed by an extra one.
+    pub fn write_fibonacci(&mut self, x: u64) {
+        assert!(x > 0, "Fibonacci codes start at 1");
+        let top = FIBONACCI.partition_point(|f| *f <= x) - 1;
+        let pos = self.grow(top + 2);
+        let mut rest = x;
+        for i in (0..=top).rev() {
+            if FIBONACCI[i] <= rest {
+                rest -= FIBONACCI[i];
+                self.bits.set(pos + i);
+            }
+        }
+        self.bits.set(pos + top + 1);
+    }
+    /// Groups of 7 bits, least significant first, each preceded by a bit
+    /// telling whether another group follows.
+    pub fn write_varint(&mut self, x: u64) {
+        let mut rest = x;
+        loop {
+            let group = rest & ((1 << VARINT_GROUP) - 1);
+            rest >>= VARINT_GROUP;
+            self.write_bit(rest > 0);
+            self.write_bits(group, VARINT_GROUP);
+            if rest == 0 {
+                return;
+            }
+        }
+    }
+    /// The bits written so far.
+    pub fn bits(&self) -> &BitVec {
+        &self.bits
+    }
+    pub fn into_bit_vec(self) -> BitVec {
+        self.bits
+    }
+}
+
+/// Reads bit fields and codes from a [`BitVec`], starting at a position that
+/// moves past every value read. Reads running past the end return `None`.
+#[derive(Debug, Clone)]
+pub struct BitReader<'bv> {
+    bits: &'bv BitVec,
+    pos: usize,
+}
+
+impl<'bv> BitReader<'bv> /* Public API */ {
+    pub fn new(bits: &'bv BitVec) -> BitReader<'bv> {
+        BitReader { bits, pos: 0 }
+    }
+    /// Position of the next bit to read.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+    pub fn seek(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+    /// Number of bits left to read.
+    pub fn remaining(&self) -> usize {
+        self.bits.len().saturating_sub(self.pos)
+    }
+    pub fn read_bit(&mut self) -> Option<bool> {
+        if self.remaining() == 0 {
+            return None;
+        }
+        self.pos += 1;
+        Some(self.bits.get(self.pos - 1))
+    }
+    /// Reads a field of `width <= 64` bits.
+    pub fn read_bits(&mut self, width: usize) -> Option<u64> {
+        assert!(width <= WORD_SIZE, "fields are at most 64 bits wide");
+        if width > self.remaining() {
+            return None;
+        }
+        self.pos += width;
+        Some(self.bits.get_bits(self.pos - width, width))
+    }
+    /// Number of zeros before the next one, which is consumed too.
+    pub fn read_unary(&mut self) -> Option<u64> {
+        let start = self.pos;
+        loop {
+            if self.remaining() == 0 {
+                self.pos = start;
+                return None;
+            }
+            let word = self.bits.get_bits(self.pos, WORD_SIZE);
+            let zeros = (word.leading_zeros() as usize).min(self.remaining());
+            self.pos += zeros;
+            if zeros < WORD_SIZE && self.remaining() > 0 {
+                self.pos += 1;
+                return Some((self.pos - start - 1) as u64);
+            }
+        }
+    }
+    pub fn read_gamma(&mut self) -> Option<u64> {
+        let start = self.pos;
+        let res = self.read_unary().and_then(|n| {
+            let rest = self.read_bits(n as usize)?;
+            Some(1_u64.checked_shl(n as u32)? | rest)
+        });
+        if res.is_none() {
+            self.pos = start;
+        }
+        res
+    }
+    pub fn read_delta(&mut self) -> Option<u64> {
+        let start = self.pos;
+        let res = self.read_gamma().and_then(|width| {
+            let n = width as usize - 1;
+            let rest = self.read_bits(n)?;
+            Some(1_u64.checked_shl(n as u32)? | rest)
+        });
+        if res.is_none() {
+            self.pos = start;
+        }
+        res
+    }
+    pub fn read_golomb(&mut self, m: u64) -> Option<u64> {
+        assert!(m > 0, "Golomb modulus must be positive");
+        let start = self.pos;
+        let b = bit_width(m - 1);
+        let cutoff = ((1_u128 << b) - m as u128) as u64;
+        let res = self.read_unary().and_then(|q| {
+            let mut r = self.read_bits(b.saturating_sub(1))?;
+            if b > 0 && r >= cutoff {
+                r = (r << 1 | self.read_bits(1)?) - cutoff;
+            }
+            Some(q * m + r)
+        });
+        if res.is_none() {
+            self.pos = start;
+        }
+        res
+    }
+    pub fn read_rice(&mut self, k: usize) -> Option<u64> {
+        let start = self.pos;
+        let res = self.read_unary().and_then(|q| {
+            let r = self.read_bits(k)?;
+            Some(q.checked_shl(k as u32).unwrap_or(0) | r)
+        });
+        if res.is_none() {
+            self.pos = start;
+        }
+        res
+    }
+    pub fn read_fibonacci(&mut self) -> Option<u64> {
+        let start = self.pos;
+        let mut res = 0_u64;
+        let mut prev = false;
+        for f in FIBONACCI.iter().chain(std::iter::once(&0)) {
+            let Some(bit) = self.read_bit() else {
+                break;
+            };
+            if bit && prev {
+                return Some(res);
+            }
+            if bit {
+                res = res.wrapping_add(*f);
+            }
+            prev = bit;
+        }
+        self.pos = start;
+        None
+    }
+    pub fn read_varint(&mut self) -> Option<u64> {
+        let start = self.pos;
+        let mut res = 0_u64;
+        let mut shift = 0;
+        loop {
+            let (Some(more), Some(group)) = (self.read_bit(), self.read_bits(VARINT_GROUP)) else {
+                self.pos = start;
+                return None;
+            };
+            res |= group.checked_shl(shift).unwrap_or(0);
+            shift += VARINT_GROUP as u32;
+            if !more {
+                return Some(res);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod bit_io_tests {
+    use crate::bit_io::{BitReader, BitWriter};
+    use crate::bit_vec::BitVec;
+    use rand::Rng;
+
+    fn to_string(bv: &BitVec) -> String {
+        (0..bv.len())
+            .map(|i| if bv.get(i) { '1' } else { '0' })
+            .collect()
+    }
+    /// Values spread over every bit width.
+    fn random_values(n: usize) -> Vec<u64> {
+        let mut rng = rand::thread_rng();
+        (0..n)
+            .map(|_| (rng.gen::<u64>() >> rng.gen_range(0..64)).max(1))
+            .collect()
+    }
+
+    #[test]
+    fn known_codes_tests() {
+        let mut w = BitWriter::new();
+        w.write_unary(3);
+        w.write_gamma(1);
+        w.write_gamma(5);
+        w.write_delta(10);
+        w.write_fibonacci(4);
+        w.write_rice(9, 2);
+        w.write_golomb(7, 3);
+        w.write_varint(300);
+        assert_eq!(
+            to_string(w.bits()),
+            [
+                "0001",
+                "1",
+                "00101",
+                "00100010",
+                "1011",
+                "00101",
+                "00110",
+                "1010110000000010"
+            ]
+            .concat()
+        );
+    }
+    #[test]
+    fn roundtrip_tests() {
+        let values = random_values(2000);
+        let mut w = BitWriter::new();
+        for x in &values {
+            w.write_gamma(*x);
+            w.write_delta(*x);
+            w.write_fibonacci(*x);
+            w.write_varint(*x);
+            w.write_bits(*x, 64);
+            w.write_bits(*x, 13);
+            w.write_rice(*x & 0xffff, 5);
+            w.write_golomb(*x & 0xfff, 10);
+            w.write_unary(*x % 100);
+        }
+        let bits = w.into_bit_vec();
+        let mut r = BitReader::new(&bits);
+        for x in &values {
+            assert_eq!(r.read_gamma(), Some(*x));
+            assert_eq!(r.read_delta(), Some(*x));
+            assert_eq!(r.read_fibonacci(), Some(*x));
+            assert_eq!(r.read_varint(), Some(*x));
+            assert_eq!(r.read_bits(64), Some(*x));
+            assert_eq!(r.read_bits(13), Some(*x & 0x1fff));
+            assert_eq!(r.read_rice(5), Some(*x & 0xffff));
+            assert_eq!(r.read_golomb(10), Some(*x & 0xfff));
+            assert_eq!(r.read_unary(), Some(*x % 100));
+        }
+        assert_eq!(r.remaining(), 0);
+        assert_eq!(r.read_bit(), None);
+    }
+    #[test]
+    fn golomb_moduli_tests() {
+        for m in [1, 2, 3, 5, 8, 100, 1 << 40, u64::MAX] {
+            let mut w = BitWriter::new();
+            for x in 0..300 {
+                w.write_golomb(x * 7, m);
+            }
+            let bits = w.into_bit_vec();
+            let mut r = BitReader::new(&bits);
+            for x in 0..300 {
+                assert_eq!(r.read_golomb(m), Some(x * 7), "modulus {}", m);
+            }
+        }
+    }
+    #[test]
+    fn rice_parameter_bounds_tests() {
+        let values = [0, 1, 5, 1 << 40, u64::MAX];
+        for k in [0, 64] {
+            let mut w = BitWriter::new();
+            for x in values.iter().filter(|x| k > 0 || **x < 100) {
+                w.write_rice(*x, k);
+            }
+            let bits = w.into_bit_vec();
+            let mut r = BitReader::new(&bits);
+            for x in values.iter().filter(|x| k > 0 || **x < 100) {
+                assert_eq!(r.read_rice(k), Some(*x), "parameter {}", k);
+            }
+            assert_eq!(r.read_rice(k), None);
+        }
+    }
+    #[test]
+    fn extreme_values_tests() {
+        let mut w = BitWriter::new();
+        for x in [1, u64::MAX, 1 << 63, (1 << 63) - 1] {
+            w.write_gamma(x);
+            w.write_delta(x);
+            w.write_fibonacci(x);
+            w.write_varint(x);
+        }
+        w.write_varint(0);
+        w.write_rice(u64::MAX >> 1, 63);
+        let bits = w.into_bit_vec();
+        let mut r = BitReader::new(&bits);
+        for x in [1, u64::MAX, 1 << 63, (1 << 63) - 1] {
+            assert_eq!(r.read_gamma(), Some(x));
+            assert_eq!(r.read_delta(), Some(x));
+            assert_eq!(r.read_fibonacci(), Some(x));
+            assert_eq!(r.read_varint(), Some(x));
+        }
+        assert_eq!(r.read_varint(), Some(0));
+        assert_eq!(r.read_rice(63), Some(u64::MAX >> 1));
+    }
+    #[test]
+    fn truncated_stream_tests() {
+        let mut w = BitWriter::new();
+        w.write_gamma(1000);
+        let bits = w.into_bit_vec();
+        let truncated = bits.extract(0, bits.len() - 1);
+        let mut r = BitReader::new(&truncated);
+        assert_eq!(r.read_gamma(), None);
+        assert_eq!(r.position(), 0);
+        assert_eq!(r.read_unary(), Some(9));
+        let zeros = BitVec::new(100);
+        let mut r = BitReader::new(&zeros);
+        assert_eq!(r.read_unary(), None);
+        assert_eq!(r.read_fibonacci(), None);
+        r.seek(50);
+        assert_eq!(r.read_bits(64), None);
+        assert_eq!(r.read_bits(50), Some(0));
+        assert_eq!(r.remaining(), 0);
+    }
+}
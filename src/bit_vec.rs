@@ -1,7 +1,12 @@
 #![allow(dead_code)]
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
-use std::ops::Add;
+use std::ops::{Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign};
+use std::ops::{Not, Shl, ShlAssign, Shr, ShrAssign, Sub, SubAssign};
+
+/// Number of bits stored in a single word of a [`BitVec`].
+pub(in crate) const WORD_SIZE: usize = 64;
 
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub(in crate) struct ByteSize(pub usize);
@@ -9,14 +14,32 @@ pub(in crate) struct ByteSize(pub usize);
 impl From<BitVecSize> for ByteSize {
     fn from(b: BitVecSize) -> Self {
         let BitVecSize(u) = b;
-        ByteSize((u as f64 / 8.0_f64).ceil() as usize)
+        ByteSize(u.div_ceil(8))
     }
 }
 impl ByteSize {
-    pub fn to_usize(&self) -> usize {
+    pub fn to_usize(self) -> usize {
         self.0
     }
-    pub fn to_u64(&self) -> u64 {
+    pub fn to_u64(self) -> u64 {
+        self.0 as u64
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
+pub(in crate) struct WordSize(pub usize);
+
+impl From<BitVecSize> for WordSize {
+    fn from(b: BitVecSize) -> Self {
+        let BitVecSize(u) = b;
+        WordSize(u.div_ceil(WORD_SIZE))
+    }
+}
+impl WordSize {
+    pub fn to_usize(self) -> usize {
+        self.0
+    }
+    pub fn to_u64(self) -> u64 {
         self.0 as u64
     }
 }
@@ -30,45 +53,140 @@ impl From<ByteSize> for BitVecSize {
         BitVecSize(u * 8)
     }
 }
+impl From<WordSize> for BitVecSize {
+    fn from(w: WordSize) -> Self {
+        let WordSize(u) = w;
+        BitVecSize(u * WORD_SIZE)
+    }
+}
 impl BitVecSize {
-    pub fn to_usize(&self) -> usize {
+    pub fn to_usize(self) -> usize {
         self.0
     }
-    pub fn to_u64(&self) -> u64 {
+    pub fn to_u64(self) -> u64 {
         self.0 as u64
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialOrd, PartialEq, Ord, Eq)]
+/// A fixed-length sequence of bits packed into `u64` words.
+///
+/// Bit order: bit `i` lives in word `i / 64`, counted from the most significant
+/// end of that word, i.e. under the mask `1 << (63 - i % 64)`. Reading the bits
+/// in index order therefore reads the words (and the numeric value used by
+/// [`BitVec::to_u64`] and [`BitVec::from_u64`]) from the most significant bit
+/// down. Bits past `size` in the last word are always kept at zero, so whole-word
+/// popcounts can be used without masking the tail; code writing `bv` directly
+/// must keep it that way.
+///
+/// As a number, a vector is an unsigned integer of exactly `size` bits. The
+/// arithmetic operators wrap around at that width and keep the width of their
+/// left operand, and vectors are ordered by value, then by length.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct BitVec {
-    pub bv: Vec<u8>,
+    pub bv: Vec<u64>,
     pub size: usize,
 }
 
+/// Mask selecting bit `i` inside its word.
+#[inline]
+pub(in crate) fn bit_mask(i: usize) -> u64 {
+    1 << (WORD_SIZE - 1 - i % WORD_SIZE)
+}
+
+/// Mask selecting the first `n` bits (in bit order) of a word, `n <= 64`.
+#[inline]
+pub(in crate) fn prefix_mask(n: usize) -> u64 {
+    if n == 0 {
+        0
+    } else {
+        u64::MAX << (WORD_SIZE - n)
+    }
+}
+
+/// Position (in bit order) of the `r`-th set bit of `word`, counting from zero.
+/// `r` must be less than `word.count_ones()`.
+#[inline]
+pub(in crate) fn select_in_word(word: u64, r: u32) -> usize {
+    debug_assert!(r < word.count_ones());
+    let mut w = word.reverse_bits();
+    let mut r = r;
+    let mut pos = 0;
+    loop {
+        let c = ((w >> pos) & 0xFF).count_ones();
+        if r < c {
+            break;
+        }
+        r -= c;
+        pos += 8;
+    }
+    w >>= pos;
+    for _ in 0..r {
+        w &= w - 1;
+    }
+    pos + w.trailing_zeros() as usize
+}
+
 impl BitVec /* Essentials */ {
     pub fn new(size: usize) -> Self {
         BitVec::new_with_zeros(size)
     }
     pub fn new_with_zeros(size: usize) -> Self {
-        let byte_size = ByteSize((size as f64 / 8.0_f64).ceil() as usize);
-        let mut bv = Vec::with_capacity(byte_size.to_usize());
-        for _ in 0..byte_size.0 {
-            bv.push(0);
-        }
+        let word_size = WordSize::from(BitVecSize(size));
+        let bv = vec![0; word_size.to_usize()];
         BitVec { bv, size }
     }
+    /// Builds a bit vector from bytes, the first bit being the most significant
+    /// bit of `vec[0]`.
     pub fn new_with_vec(vec: Vec<u8>) -> Self {
         let size = BitVecSize::from(ByteSize(vec.len())).to_usize();
-        let bv = vec;
+        let bv = vec
+            .chunks(8)
+            .map(|chunk| {
+                let mut bytes = [0; 8];
+                bytes[..chunk.len()].copy_from_slice(chunk);
+                u64::from_be_bytes(bytes)
+            })
+            .collect();
         BitVec { bv, size }
     }
+    /// Builds a bit vector of `size` bits from words laid out in [`BitVec`] bit
+    /// order. Missing words are zero, bits past `size` are discarded.
+    pub fn new_with_words(words: Vec<u64>, size: usize) -> Self {
+        let mut bv = words;
+        bv.resize(WordSize::from(BitVecSize(size)).to_usize(), 0);
+        let mut b = BitVec { bv, size };
+        b.clear_tail();
+        b
+    }
     pub fn new_with_random(size: usize) -> Self {
-        let byte_size = ByteSize::from(BitVecSize(size));
-        let mut bv = Vec::with_capacity(byte_size.to_usize());
-        for _ in 0..byte_size.to_usize() {
-            bv.push(rand::random());
+        let word_size = WordSize::from(BitVecSize(size));
+        let bv = (0..word_size.to_usize()).map(|_| rand::random()).collect();
+        let mut b = BitVec { bv, size };
+        b.clear_tail();
+        b
+    }
+    fn clear_tail(&mut self) {
+        let used = self.size % WORD_SIZE;
+        if used != 0 {
+            if let Some(last) = self.bv.last_mut() {
+                *last &= prefix_mask(used);
+            }
         }
-        BitVec { bv, size }
+    }
+}
+impl BitVec /* Size */ {
+    pub fn len(&self) -> usize {
+        self.size
+    }
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+    /// The underlying words, in [`BitVec`] bit order.
+    pub fn words(&self) -> &[u64] {
+        &self.bv
+    }
+    pub fn num_words(&self) -> usize {
+        self.bv.len()
     }
 }
 impl BitVec /* Operations */ {
@@ -77,117 +195,888 @@ impl BitVec /* Operations */ {
         self.get_bool(i)
     }
     fn get_bool(&self, i: usize) -> bool {
-        let pri_ind = i / 8;
-        let snd_ind = i % 8;
-        let padding = if pri_ind == 0 { (self.size - 1) % 8 } else { 7 };
-        if let Some(b) = self.bv.get(pri_ind) {
-            (b & (0b00000001 << (padding - snd_ind))) != 0
-        } else {
-            // println!("Rank Position Exceeds Size!");
-            // println!("(Position: {:?}, Size: {:?})", i, self.size);
-            false
+        if i >= self.size {
+            return false;
         }
+        self.bv[i / WORD_SIZE] & bit_mask(i) != 0
     }
     pub fn get_u8(&self, i: usize) -> u8 {
         self.get_bool(i) as u8
     }
+    /// Reads `width <= 64` bits starting at `pos` as a number whose most
+    /// significant bit is bit `pos`. Bits past the end read as zero.
+    pub(crate) fn get_bits(&self, pos: usize, width: usize) -> u64 {
+        if width == 0 {
+            return 0;
+        }
+        let w = pos / WORD_SIZE;
+        let off = pos % WORD_SIZE;
+        let word = |k: usize| self.bv.get(k).copied().unwrap_or(0);
+        let mut val = word(w) << off;
+        if off + width > WORD_SIZE {
+            val |= word(w + 1) >> (WORD_SIZE - off);
+        }
+        val >> (WORD_SIZE - width)
+    }
     // Setters
+    /// Sets bit `i`, panicking if `i` is out of range.
     pub fn set(&mut self, i: usize) {
         self.set_bool(i);
     }
     pub(crate) fn set_bool(&mut self, i: usize) {
-        let pri_ind = i / 8;
-        let snd_ind = i % 8;
-        let padding = if pri_ind == 0 { (self.size - 1) % 8 } else { 7 };
-        if let Some(b) = self.bv.get(pri_ind) {
-            self.bv[pri_ind] = b | (0b00000001 << (padding - snd_ind));
+        self.check_index(i, self.size);
+        self.bv[i / WORD_SIZE] |= bit_mask(i);
+    }
+    /// Clears bit `i`. An out of range index panics, as it does for
+    /// [`BitVec::clear_range`].
+    pub fn clear(&mut self, i: usize) {
+        self.check_index(i, self.size);
+        self.bv[i / WORD_SIZE] &= !bit_mask(i);
+    }
+    /// Flips bit `i`, panicking if `i` is out of range.
+    pub fn flip(&mut self, i: usize) {
+        self.check_index(i, self.size);
+        self.bv[i / WORD_SIZE] ^= bit_mask(i);
+    }
+    /// Sets bit `i` to `bit`, panicking if `i` is out of range either way.
+    pub fn assign(&mut self, i: usize, bit: bool) {
+        self.check_index(i, self.size);
+        if bit {
+            self.set_bool(i);
+        } else {
+            self.clear(i);
+        }
+    }
+    /// Applies `op(word, mask)` to the words covering `[left, right)`, `mask`
+    /// selecting the bits of the word inside the range. Only the first and
+    /// last words get partial masks.
+    fn update_range(&mut self, left: usize, right: usize, op: impl Fn(u64, u64) -> u64) {
+        assert!(
+            left <= right && right <= self.size,
+            "range [{}, {}) out of bounds for length {}",
+            left,
+            right,
+            self.size
+        );
+        if left == right {
+            return;
+        }
+        let (lw, rw) = (left / WORD_SIZE, (right - 1) / WORD_SIZE);
+        let head = !prefix_mask(left % WORD_SIZE);
+        let tail = prefix_mask((right - 1) % WORD_SIZE + 1);
+        if lw == rw {
+            self.bv[lw] = op(self.bv[lw], head & tail);
+            return;
+        }
+        self.bv[lw] = op(self.bv[lw], head);
+        for w in &mut self.bv[lw + 1..rw] {
+            *w = op(*w, u64::MAX);
+        }
+        self.bv[rw] = op(self.bv[rw], tail);
+    }
+    /// Sets the bits in `[left, right)`.
+    pub fn set_range(&mut self, left: usize, right: usize) {
+        self.update_range(left, right, |w, m| w | m);
+    }
+    /// Clears the bits in `[left, right)`.
+    pub fn clear_range(&mut self, left: usize, right: usize) {
+        self.update_range(left, right, |w, m| w & !m);
+    }
+    /// Flips the bits in `[left, right)`.
+    pub fn flip_range(&mut self, left: usize, right: usize) {
+        self.update_range(left, right, |w, m| w ^ m);
+    }
+    /// Writes the low `width <= 64` bits of `value` at `pos`, most significant
+    /// bit first. The range must lie within the vector.
+    pub(crate) fn set_bits(&mut self, pos: usize, width: usize, value: u64) {
+        if width == 0 {
+            return;
+        }
+        debug_assert!(pos + width <= self.size);
+        let w = pos / WORD_SIZE;
+        let off = pos % WORD_SIZE;
+        let mask = prefix_mask(width);
+        let aligned = (value << (WORD_SIZE - width)) & mask;
+        self.bv[w] = (self.bv[w] & !(mask >> off)) | (aligned >> off);
+        if off + width > WORD_SIZE {
+            let shift = WORD_SIZE - off;
+            self.bv[w + 1] = (self.bv[w + 1] & !(mask << shift)) | (aligned << shift);
         }
     }
+    // Counting
+    pub fn count_ones(&self) -> u64 {
+        self.bv.iter().map(|w| w.count_ones() as u64).sum()
+    }
+    pub fn count_zeros(&self) -> u64 {
+        self.size as u64 - self.count_ones()
+    }
+    /// Number of set bits in `[left, right)`, counted a word at a time.
+    pub(crate) fn count_ones_in(&self, left: usize, right: usize) -> u64 {
+        let right = right.min(self.size);
+        if left >= right {
+            return 0;
+        }
+        let (lw, rw) = (left / WORD_SIZE, (right - 1) / WORD_SIZE);
+        let head = !prefix_mask(left % WORD_SIZE);
+        let tail = prefix_mask((right - 1) % WORD_SIZE + 1);
+        if lw == rw {
+            return (self.bv[lw] & head & tail).count_ones() as u64;
+        }
+        let mut count = (self.bv[lw] & head).count_ones() as u64;
+        for w in &self.bv[lw + 1..rw] {
+            count += w.count_ones() as u64;
+        }
+        count + (self.bv[rw] & tail).count_ones() as u64
+    }
     // Slicing
     fn concat(&self, other: &Self) -> Self {
-        let bv = [self.clone().bv, other.clone().bv].concat();
-        let size = BitVecSize::from(ByteSize(bv.len())).to_usize();
-        Self { bv, size }
+        let mut res = self.clone();
+        res.extend_from_bit_vec(other);
+        res
     }
     pub fn extract(&self, left: usize, right: usize) -> Self {
         if left > right {
-            panic!()
+            panic!("extract: left ({}) > right ({})", left, right)
         }
-        let mut val = 0;
-        for i in left..right {
-            val += 2_i32.pow((right - i - 1) as u32) * self.get_u8(i) as i32;
+        let mut res = BitVec::new_with_zeros(right - left);
+        res.copy_bits_from(self, left, 0, right - left);
+        res
+    }
+    /// Copies `len` bits of `src` starting at `from` into `self` starting at `to`.
+    fn copy_bits_from(&mut self, src: &BitVec, from: usize, to: usize, len: usize) {
+        let mut done = 0;
+        while done < len {
+            let width = (len - done).min(WORD_SIZE);
+            self.set_bits(to + done, width, src.get_bits(from + done, width));
+            done += width;
         }
-        BitVec::from_u64(val as u64, right - left)
     }
     // Ops
-    fn incr(self) -> Self {
+    /// The vector plus one, wrapping around to zero.
+    pub fn incr(self) -> Self {
         BitVec::from_u64(1, self.size) + self
     }
-}
-impl BitVec /* Transform to u64 */ {
-    fn zero_extend(&self) -> Self {
-        let size = self.bv.len();
-        if size >= 8 {
-            self.clone()
-        } else {
-            let remain = 8 - size;
-            let zeros: BitVec =
-                BitVec::new_with_zeros(BitVecSize::from(ByteSize(remain)).to_usize());
-            BitVec::concat(&zeros, &self)
+    /// Combines `rhs` into `self` word by word. The shorter operand is padded
+    /// with zeros at its end, so the result has the larger of the two lengths.
+    fn zip_words(&mut self, rhs: &BitVec, op: impl Fn(u64, u64) -> u64) {
+        if rhs.size > self.size {
+            self.size = rhs.size;
+            self.bv.resize(rhs.bv.len(), 0);
+        }
+        for (i, w) in self.bv.iter_mut().enumerate() {
+            *w = op(*w, rhs.bv.get(i).copied().unwrap_or(0));
         }
     }
+}
+impl BitVec /* Transform to u64 */ {
+    /// The numeric value of the vector, truncated to its last 64 bits.
     pub fn to_u64(&self) -> u64 {
-        let bv = self.zero_extend();
-        let bytes = &bv.bv[..];
-        u64::from_be_bytes([
-            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
-        ])
+        let width = self.size.min(WORD_SIZE);
+        self.get_bits(self.size - width, width)
     }
+    /// A `size` bit vector holding `u`, truncated to `size` bits.
     pub fn from_u64(u: u64, size: usize) -> Self {
-        let bytes = u.to_be_bytes();
-        let bv: Vec<u8> = bytes.to_vec();
-        let vec_size = bv.len();
-        let empty_bytes = vec_size - ByteSize::from(BitVecSize(size)).to_usize();
-        let bv = bv[empty_bytes..].to_vec();
-        BitVec { bv, size }
+        let mut bv = BitVec::new_with_zeros(size);
+        let width = size.min(WORD_SIZE);
+        bv.set_bits(size - width, width, u);
+        bv
+    }
+}
+impl BitVec /* Iteration */ {
+    /// End of the run of `bit`s starting at `pos`, scanning a word at a time.
+    pub(in crate) fn run_end(&self, pos: usize, bit: bool) -> usize {
+        let mut p = pos;
+        while p < self.size {
+            let word = self.get_bits(p, WORD_SIZE);
+            let word = if bit { !word } else { word };
+            let same = (word.leading_zeros() as usize).min(self.size - p);
+            p += same;
+            if same < WORD_SIZE {
+                break;
+            }
+        }
+        p
+    }
+    /// Start of the run of `bit`s ending just before `end`.
+    pub(in crate) fn run_start(&self, end: usize, bit: bool) -> usize {
+        let mut p = end;
+        while p > 0 {
+            let width = p.min(WORD_SIZE);
+            let word = self.get_bits(p - width, width);
+            let word = if bit { !word } else { word };
+            let same = (word.trailing_zeros() as usize).min(width);
+            p -= same;
+            if same < width {
+                break;
+            }
+        }
+        p
+    }
+    /// The bits, in order.
+    pub fn iter(&self) -> BitVecIter<'_> {
+        BitVecIter {
+            bv: self,
+            front: 0,
+            back: self.size,
+        }
+    }
+    /// Positions of the ones, in increasing order, skipping zero words.
+    pub fn iter_ones(&self) -> BitPositions<'_> {
+        BitPositions {
+            bv: self,
+            flip: 0,
+            front: 0,
+            back: self.size,
+        }
+    }
+    /// Positions of the zeros, in increasing order, skipping all-ones words.
+    pub fn iter_zeros(&self) -> BitPositions<'_> {
+        BitPositions {
+            bv: self,
+            flip: u64::MAX,
+            front: 0,
+            back: self.size,
+        }
+    }
+    /// Maximal runs of equal bits as `(bit, start, len)`, in order.
+    pub fn runs(&self) -> BitRuns<'_> {
+        BitRuns {
+            bv: self,
+            front: 0,
+            back: self.size,
+        }
+    }
+}
+impl BitVec /* Growth */ {
+    fn check_index(&self, i: usize, len: usize) {
+        assert!(i < len, "index {} out of range, must be less than {}", i, len);
+    }
+    /// Appends `bit`, growing the word vector by doubling its capacity.
+    pub fn push(&mut self, bit: bool) {
+        if self.size.is_multiple_of(WORD_SIZE) {
+            self.bv.push(0);
+        }
+        self.size += 1;
+        if bit {
+            self.set_bool(self.size - 1);
+        }
+    }
+    /// Removes the last bit and returns it.
+    pub fn pop(&mut self) -> Option<bool> {
+        if self.size == 0 {
+            return None;
+        }
+        let bit = self.get(self.size - 1);
+        self.truncate(self.size - 1);
+        Some(bit)
+    }
+    /// Shortens the vector to its first `len` bits, if it is longer.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.size {
+            self.size = len;
+            self.bv.truncate(WordSize::from(BitVecSize(len)).to_usize());
+            self.clear_tail();
+        }
+    }
+    /// Truncates the vector to `len` bits, or fills it up to `len` with `fill`.
+    pub fn resize(&mut self, len: usize, fill: bool) {
+        if len <= self.size {
+            self.truncate(len);
+            return;
+        }
+        if fill && !self.size.is_multiple_of(WORD_SIZE) {
+            *self.bv.last_mut().unwrap() |= !prefix_mask(self.size % WORD_SIZE);
+        }
+        let fill_word = if fill { u64::MAX } else { 0 };
+        self.bv
+            .resize(WordSize::from(BitVecSize(len)).to_usize(), fill_word);
+        self.size = len;
+        self.clear_tail();
+    }
+    /// Appends the bits of `other`, a word at a time.
+    pub fn extend_from_bit_vec(&mut self, other: &BitVec) {
+        let old = self.size;
+        self.resize(old + other.size, false);
+        self.copy_bits_from(other, 0, old, other.size);
+    }
+    /// Inserts `bit` before position `i <= len`, shifting the following bits.
+    pub fn insert(&mut self, i: usize, bit: bool) {
+        self.check_index(i, self.size + 1);
+        self.push(false);
+        let (w, off) = (i / WORD_SIZE, i % WORD_SIZE);
+        for k in (w + 1..self.bv.len()).rev() {
+            self.bv[k] = self.bv[k] >> 1 | self.bv[k - 1] << (WORD_SIZE - 1);
+        }
+        let keep = prefix_mask(off);
+        self.bv[w] = (self.bv[w] & keep) | (self.bv[w] & !keep) >> 1;
+        if bit {
+            self.set_bool(i);
+        }
+    }
+    /// Removes the bit at `i < len` and returns it, shifting the following bits.
+    pub fn remove(&mut self, i: usize) -> bool {
+        self.check_index(i, self.size);
+        let bit = self.get(i);
+        let (w, off) = (i / WORD_SIZE, i % WORD_SIZE);
+        let next = |bv: &[u64], k: usize| bv.get(k + 1).map_or(0, |x| x >> (WORD_SIZE - 1));
+        let keep = prefix_mask(off);
+        self.bv[w] = (self.bv[w] & keep) | (self.bv[w] << 1 & !keep) | next(&self.bv, w);
+        for k in w + 1..self.bv.len() {
+            self.bv[k] = self.bv[k] << 1 | next(&self.bv, k);
+        }
+        self.truncate(self.size - 1);
+        bit
+    }
+}
+impl Extend<bool> for BitVec {
+    fn extend<I: IntoIterator<Item = bool>>(&mut self, iter: I) {
+        for bit in iter {
+            self.push(bit);
+        }
+    }
+}
+impl<'a> Extend<&'a bool> for BitVec {
+    fn extend<I: IntoIterator<Item = &'a bool>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+impl FromIterator<bool> for BitVec {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> BitVec {
+        let mut res = BitVec::new(0);
+        res.extend(iter);
+        res
+    }
+}
+impl BitVec /* Arithmetic */ {
+    /// Number of 64-bit limbs of the numeric value.
+    fn num_limbs(&self) -> usize {
+        self.bv.len()
+    }
+    /// The `k`-th 64-bit limb of the numeric value, least significant first.
+    fn limb(&self, k: usize) -> u64 {
+        let end = self.size.saturating_sub(k * WORD_SIZE);
+        let start = end.saturating_sub(WORD_SIZE);
+        self.get_bits(start, end - start)
+    }
+    /// A `size` bit vector holding `limbs`, least significant first, and
+    /// whether the value fit in `size` bits.
+    fn from_limbs(limbs: &[u64], size: usize) -> (BitVec, bool) {
+        let mut res = BitVec::new_with_zeros(size);
+        for (k, limb) in limbs.iter().enumerate().take(res.num_limbs()) {
+            let end = size - k * WORD_SIZE;
+            let start = end.saturating_sub(WORD_SIZE);
+            res.set_bits(start, end - start, *limb);
+        }
+        let fits = limbs.iter().enumerate().all(|(k, limb)| {
+            let width = size.saturating_sub(k * WORD_SIZE);
+            width >= WORD_SIZE || limb >> width == 0
+        });
+        (res, fits)
+    }
+    /// `self + rhs` truncated to the width of `self`, and whether it overflowed.
+    pub fn overflowing_add(&self, rhs: &BitVec) -> (BitVec, bool) {
+        let n = self.num_limbs().max(rhs.num_limbs());
+        let mut limbs = Vec::with_capacity(n + 1);
+        let mut carry = false;
+        for k in 0..n {
+            let (s, c1) = self.limb(k).overflowing_add(rhs.limb(k));
+            let (s, c2) = s.overflowing_add(carry as u64);
+            limbs.push(s);
+            carry = c1 || c2;
+        }
+        limbs.push(carry as u64);
+        let (res, fits) = BitVec::from_limbs(&limbs, self.size);
+        (res, !fits)
+    }
+    /// `self - rhs` modulo `2^len`, and whether `rhs` was larger.
+    pub fn overflowing_sub(&self, rhs: &BitVec) -> (BitVec, bool) {
+        let n = self.num_limbs().max(rhs.num_limbs());
+        let mut limbs = Vec::with_capacity(n);
+        let mut borrow = false;
+        for k in 0..n {
+            let (d, b1) = self.limb(k).overflowing_sub(rhs.limb(k));
+            let (d, b2) = d.overflowing_sub(borrow as u64);
+            limbs.push(d);
+            borrow = b1 || b2;
+        }
+        (BitVec::from_limbs(&limbs, self.size).0, borrow)
+    }
+    pub fn wrapping_add(&self, rhs: &BitVec) -> BitVec {
+        self.overflowing_add(rhs).0
+    }
+    pub fn wrapping_sub(&self, rhs: &BitVec) -> BitVec {
+        self.overflowing_sub(rhs).0
+    }
+    /// `self + rhs`, or `None` if it does not fit in the width of `self`.
+    pub fn checked_add(&self, rhs: &BitVec) -> Option<BitVec> {
+        match self.overflowing_add(rhs) {
+            (res, false) => Some(res),
+            (_, true) => None,
+        }
+    }
+    /// `self - rhs`, or `None` if `rhs` is larger.
+    pub fn checked_sub(&self, rhs: &BitVec) -> Option<BitVec> {
+        match self.overflowing_sub(rhs) {
+            (res, false) => Some(res),
+            (_, true) => None,
+        }
+    }
+    /// `self + rhs`, or all ones if it does not fit in the width of `self`.
+    pub fn saturating_add(&self, rhs: &BitVec) -> BitVec {
+        self.checked_add(rhs).unwrap_or_else(|| !BitVec::new_with_zeros(self.size))
+    }
+    /// `self - rhs`, or zero if `rhs` is larger.
+    pub fn saturating_sub(&self, rhs: &BitVec) -> BitVec {
+        self.checked_sub(rhs).unwrap_or_else(|| BitVec::new_with_zeros(self.size))
+    }
+    /// Rotates the value `n` bits towards its most significant end, which moves
+    /// bit `i` to index `i - n` modulo the length.
+    pub fn rotate_left(&self, n: usize) -> BitVec {
+        let mut res = BitVec::new_with_zeros(self.size);
+        if self.size > 0 {
+            let n = n % self.size;
+            res.copy_bits_from(self, n, 0, self.size - n);
+            res.copy_bits_from(self, 0, self.size - n, n);
+        }
+        res
+    }
+    /// Rotates the value `n` bits towards its least significant end.
+    pub fn rotate_right(&self, n: usize) -> BitVec {
+        match self.size {
+            0 => self.clone(),
+            size => self.rotate_left(size - n % size),
+        }
+    }
+    /// Compares the numeric values, whatever the lengths.
+    pub fn cmp_value(&self, other: &BitVec) -> Ordering {
+        let n = self.num_limbs().max(other.num_limbs());
+        (0..n)
+            .rev()
+            .map(|k| self.limb(k).cmp(&other.limb(k)))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+impl Ord for BitVec {
+    fn cmp(&self, other: &BitVec) -> Ordering {
+        self.cmp_value(other).then(self.size.cmp(&other.size))
+    }
+}
+impl PartialOrd for BitVec {
+    fn partial_cmp(&self, other: &BitVec) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
-impl Add for BitVec {
-    type Output = Self;
+/// Wrapping `+` and `-`, keeping the width of the left operand.
+macro_rules! arithmetic_op {
+    ($Op:ident, $op:ident, $OpAssign:ident, $op_assign:ident, $wrapping:ident) => {
+        impl $Op<&BitVec> for &BitVec {
+            type Output = BitVec;
 
-    fn add(self, rhs: BitVec) -> Self {
-        BitVec::from_u64(self.to_u64() + rhs.to_u64(), self.size)
+            fn $op(self, rhs: &BitVec) -> BitVec {
+                self.$wrapping(rhs)
+            }
+        }
+        impl $Op<&BitVec> for BitVec {
+            type Output = BitVec;
+
+            fn $op(self, rhs: &BitVec) -> BitVec {
+                self.$wrapping(rhs)
+            }
+        }
+        impl $Op for BitVec {
+            type Output = BitVec;
+
+            fn $op(self, rhs: BitVec) -> BitVec {
+                self.$wrapping(&rhs)
+            }
+        }
+        impl $OpAssign<&BitVec> for BitVec {
+            fn $op_assign(&mut self, rhs: &BitVec) {
+                *self = self.$wrapping(rhs);
+            }
+        }
+        impl $OpAssign for BitVec {
+            fn $op_assign(&mut self, rhs: BitVec) {
+                *self = self.$wrapping(&rhs);
+            }
+        }
+    };
+}
+arithmetic_op!(Add, add, AddAssign, add_assign, wrapping_add);
+arithmetic_op!(Sub, sub, SubAssign, sub_assign, wrapping_sub);
+/// Logical shift of the value towards its most significant end, moving bit `i`
+/// to index `i - n`. Bits shifted out are lost and zeros are shifted in.
+impl Shl<usize> for &BitVec {
+    type Output = BitVec;
+
+    fn shl(self, n: usize) -> BitVec {
+        let mut res = BitVec::new_with_zeros(self.size);
+        if n < self.size {
+            res.copy_bits_from(self, n, 0, self.size - n);
+        }
+        res
+    }
+}
+/// Logical shift of the value towards its least significant end.
+impl Shr<usize> for &BitVec {
+    type Output = BitVec;
+
+    fn shr(self, n: usize) -> BitVec {
+        let mut res = BitVec::new_with_zeros(self.size);
+        if n < self.size {
+            res.copy_bits_from(self, 0, n, self.size - n);
+        }
+        res
+    }
+}
+impl Shl<usize> for BitVec {
+    type Output = BitVec;
+
+    fn shl(self, n: usize) -> BitVec {
+        &self << n
+    }
+}
+impl Shr<usize> for BitVec {
+    type Output = BitVec;
+
+    fn shr(self, n: usize) -> BitVec {
+        &self >> n
+    }
+}
+impl ShlAssign<usize> for BitVec {
+    fn shl_assign(&mut self, n: usize) {
+        *self = &*self << n;
+    }
+}
+impl ShrAssign<usize> for BitVec {
+    fn shr_assign(&mut self, n: usize) {
+        *self = &*self >> n;
     }
 }
+/// Bitwise operators on owned and borrowed vectors, indexwise on bits. Vectors
+/// of different lengths are combined as if the shorter one ended with zeros.
+macro_rules! bitwise_op {
+    ($Op:ident, $op:ident, $OpAssign:ident, $op_assign:ident, $f:expr) => {
+        impl $OpAssign<&BitVec> for BitVec {
+            fn $op_assign(&mut self, rhs: &BitVec) {
+                self.zip_words(rhs, $f);
+            }
+        }
+        impl $OpAssign for BitVec {
+            fn $op_assign(&mut self, rhs: BitVec) {
+                self.$op_assign(&rhs);
+            }
+        }
+        impl $Op<&BitVec> for BitVec {
+            type Output = BitVec;
+
+            fn $op(mut self, rhs: &BitVec) -> BitVec {
+                self.$op_assign(rhs);
+                self
+            }
+        }
+        impl $Op for BitVec {
+            type Output = BitVec;
+
+            fn $op(self, rhs: BitVec) -> BitVec {
+                self.$op(&rhs)
+            }
+        }
+        impl $Op<&BitVec> for &BitVec {
+            type Output = BitVec;
+
+            fn $op(self, rhs: &BitVec) -> BitVec {
+                self.clone().$op(rhs)
+            }
+        }
+        impl $Op<BitVec> for &BitVec {
+            type Output = BitVec;
+
+            fn $op(self, rhs: BitVec) -> BitVec {
+                rhs.$op(self)
+            }
+        }
+    };
+}
+bitwise_op!(BitAnd, bitand, BitAndAssign, bitand_assign, |a, b| a & b);
+bitwise_op!(BitOr, bitor, BitOrAssign, bitor_assign, |a, b| a | b);
+bitwise_op!(BitXor, bitxor, BitXorAssign, bitxor_assign, |a, b| a ^ b);
+impl Not for BitVec {
+    type Output = BitVec;
+
+    fn not(mut self) -> BitVec {
+        for w in self.bv.iter_mut() {
+            *w = !*w;
+        }
+        self.clear_tail();
+        self
+    }
+}
+impl Not for &BitVec {
+    type Output = BitVec;
+
+    fn not(self) -> BitVec {
+        !self.clone()
+    }
+}
+/// Iterator over the bits of a [`BitVec`].
+pub struct BitVecIter<'a> {
+    bv: &'a BitVec,
+    front: usize,
+    back: usize,
+}
+
+impl Iterator for BitVecIter<'_> {
+    type Item = bool;
+    fn next(&mut self) -> Option<bool> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        Some(self.bv.get(self.front - 1))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.front;
+        (remaining, Some(remaining))
+    }
+}
+
+impl DoubleEndedIterator for BitVecIter<'_> {
+    fn next_back(&mut self) -> Option<bool> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.bv.get(self.back))
+    }
+}
+
+impl ExactSizeIterator for BitVecIter<'_> {}
+
+impl<'a> IntoIterator for &'a BitVec {
+    type Item = bool;
+    type IntoIter = BitVecIter<'a>;
+    fn into_iter(self) -> BitVecIter<'a> {
+        self.iter()
+    }
+}
+
+/// Iterator over the positions of the ones, or of the zeros, of a [`BitVec`].
+pub struct BitPositions<'a> {
+    bv: &'a BitVec,
+    /// Xored into every word, all ones to look for zeros.
+    flip: u64,
+    front: usize,
+    back: usize,
+}
+
+impl BitPositions<'_> {
+    /// Word `w`, flipped, restricted to the positions in `[front, back)`.
+    fn word(&self, w: usize) -> u64 {
+        let mut word = self.bv.bv[w] ^ self.flip;
+        if w == self.front / WORD_SIZE {
+            word &= !prefix_mask(self.front % WORD_SIZE);
+        }
+        if w == (self.back - 1) / WORD_SIZE {
+            word &= prefix_mask((self.back - 1) % WORD_SIZE + 1);
+        }
+        word
+    }
+}
+
+impl Iterator for BitPositions<'_> {
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        while self.front < self.back {
+            let w = self.front / WORD_SIZE;
+            let word = self.word(w);
+            if word != 0 {
+                let pos = w * WORD_SIZE + word.leading_zeros() as usize;
+                self.front = pos + 1;
+                return Some(pos);
+            }
+            self.front = (w + 1) * WORD_SIZE;
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.back.saturating_sub(self.front)))
+    }
+}
+
+impl DoubleEndedIterator for BitPositions<'_> {
+    fn next_back(&mut self) -> Option<usize> {
+        while self.front < self.back {
+            let w = (self.back - 1) / WORD_SIZE;
+            let word = self.word(w);
+            if word != 0 {
+                let pos = w * WORD_SIZE + WORD_SIZE - 1 - word.trailing_zeros() as usize;
+                self.back = pos;
+                return Some(pos);
+            }
+            self.back = w * WORD_SIZE;
+        }
+        None
+    }
+}
+
+/// Iterator over the maximal runs of a [`BitVec`], see [`BitVec::runs`].
+pub struct BitRuns<'a> {
+    bv: &'a BitVec,
+    front: usize,
+    back: usize,
+}
+
+impl Iterator for BitRuns<'_> {
+    type Item = (bool, usize, usize);
+    fn next(&mut self) -> Option<(bool, usize, usize)> {
+        if self.front == self.back {
+            return None;
+        }
+        let (start, bit) = (self.front, self.bv.get(self.front));
+        self.front = self.bv.run_end(start, bit).min(self.back);
+        Some((bit, start, self.front - start))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.front;
+        (remaining.min(1), Some(remaining))
+    }
+}
+
+impl DoubleEndedIterator for BitRuns<'_> {
+    fn next_back(&mut self) -> Option<(bool, usize, usize)> {
+        if self.front == self.back {
+            return None;
+        }
+        let (end, bit) = (self.back, self.bv.get(self.back - 1));
+        self.back = self.bv.run_start(end, bit).max(self.front);
+        Some((bit, self.back, end - self.back))
+    }
+}
+
 impl Display for BitVec {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "BitVec({}): [", self.size)?;
-        for byte in &self.bv[..self.bv.len() - 1] {
-            write!(f, "{:08b} ", byte)?;
+        for i in 0..self.size {
+            if i > 0 && i % 8 == 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", self.get_u8(i))?;
         }
-        write!(f, "{:08b}]", self.bv[self.bv.len() - 1])
+        write!(f, "]")
     }
 }
 
 #[cfg(test)]
 mod essentials_test {
+    use crate::*;
     #[test]
-    fn test_new() {}
+    fn test_new() {
+        for size in [0, 1, 7, 63, 64, 65, 200] {
+            let b = BitVec::new(size);
+            assert_eq!(b.len(), size);
+            assert_eq!(b.count_ones(), 0);
+            assert!((0..size).all(|i| !b.get(i)));
+        }
+    }
     #[test]
-    fn test_new_with_vec() {}
+    fn test_new_with_vec() {
+        let b = BitVec::new_with_vec(vec![0b10010001, 0b10000001]);
+        assert_eq!(b.len(), 16);
+        let expected = [1, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 1];
+        for (i, bit) in expected.iter().enumerate() {
+            assert_eq!(b.get_u8(i), *bit, "bit {}", i);
+        }
+    }
     #[test]
-    fn test_new_with_random() {}
+    fn test_new_with_random() {
+        for size in [1, 13, 64, 100, 1000] {
+            let b = BitVec::new_with_random(size);
+            assert_eq!(b.len(), size);
+            assert_eq!(b.num_words(), size.div_ceil(64));
+            let tail = size % 64;
+            if tail != 0 {
+                assert_eq!(b.words()[b.num_words() - 1] << tail, 0);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod operations_test {
     use crate::*;
+    use std::cmp::Ordering;
     #[test]
-    fn test_get_bool() {}
+    fn test_get_bool() {
+        for size in [1, 5, 13, 64, 70, 129] {
+            let mut b = BitVec::new(size);
+            for i in (0..size).step_by(3) {
+                b.set(i);
+            }
+            for i in 0..size {
+                assert_eq!(b.get(i), i % 3 == 0, "size {} bit {}", size, i);
+            }
+            assert!(!b.get(size));
+        }
+    }
     #[test]
-    fn test_get_u8() {}
+    fn test_get_u8() {
+        let b = BitVec::from_u64(0b101, 3);
+        assert_eq!((b.get_u8(0), b.get_u8(1), b.get_u8(2)), (1, 0, 1));
+    }
     #[test]
-    fn test_concat() {}
+    fn test_concat() {
+        let b1 = BitVec::from_u64(0b101, 3);
+        let b2 = BitVec::new_with_random(130);
+        let c = b1.concat(&b2);
+        assert_eq!(c.len(), 133);
+        for i in 0..3 {
+            assert_eq!(c.get(i), b1.get(i));
+        }
+        for i in 0..130 {
+            assert_eq!(c.get(i + 3), b2.get(i));
+        }
+    }
+    #[test]
+    fn test_extract() {
+        let b = BitVec::new_with_random(300);
+        for (left, right) in [(0, 0), (0, 7), (3, 64), (60, 70), (5, 290)] {
+            let e = b.extract(left, right);
+            assert_eq!(e.len(), right - left);
+            for i in left..right {
+                assert_eq!(e.get(i - left), b.get(i));
+            }
+        }
+        assert_eq!(BitVec::new_with_vec(vec![0b10010001]).extract(0, 4).to_u64(), 0b1001);
+    }
+    #[test]
+    fn test_u64_roundtrip() {
+        for size in [1, 5, 8, 33, 64] {
+            let u = rand::random::<u64>() >> (64 - size);
+            let b = BitVec::from_u64(u, size);
+            assert_eq!(b.to_u64(), u);
+        }
+    }
+    #[test]
+    fn test_count_ones_in() {
+        let b = BitVec::new_with_random(500);
+        for (left, right) in [(0, 500), (0, 1), (63, 65), (10, 400), (128, 192), (499, 600)] {
+            let expected = (left..right.min(500)).filter(|&i| b.get(i)).count() as u64;
+            assert_eq!(b.count_ones_in(left, right), expected);
+        }
+    }
+    #[test]
+    fn test_select_in_word() {
+        for _ in 0..100 {
+            let word: u64 = rand::random();
+            let b = BitVec::new_with_words(vec![word], 64);
+            let ones: Vec<usize> = (0..64).filter(|&i| b.get(i)).collect();
+            for (r, pos) in ones.iter().enumerate() {
+                assert_eq!(crate::bit_vec::select_in_word(word, r as u32), *pos);
+            }
+        }
+    }
     #[test]
     fn test_incr() {
         let bv1 = BitVec::new_with_vec(vec![0b10010001, 0b10000001]);
@@ -205,4 +1094,364 @@ mod operations_test {
         let addition = bv1 + bv2;
         assert_eq!(addition, BitVec::new_with_vec(vec![0b00010011, 0b00000010]));
     }
+    /// A `size <= 128` bit vector holding `u` truncated to `size` bits.
+    fn from_u128(u: u128, size: usize) -> BitVec {
+        let lo = BitVec::from_u64(u as u64, size.min(64));
+        if size <= 64 {
+            return lo;
+        }
+        BitVec::from_u64((u >> 64) as u64, size - 64).concat(&lo)
+    }
+    fn to_u128(b: &BitVec) -> u128 {
+        (b.limb(1) as u128) << 64 | b.limb(0) as u128
+    }
+    #[test]
+    fn test_wide_arithmetic() {
+        for size in [1, 7, 64, 65, 100, 128] {
+            let max = u128::MAX >> (128 - size);
+            for _ in 0..200 {
+                let (x, y) = (rand::random::<u128>() & max, rand::random::<u128>() & max);
+                let (a, b) = (from_u128(x, size), from_u128(y, size));
+                assert_eq!(to_u128(&a), x);
+                assert_eq!(to_u128(&(&a + &b)), x.wrapping_add(y) & max);
+                assert_eq!(to_u128(&(&a - &b)), x.wrapping_sub(y) & max);
+                let sum = x.checked_add(y).filter(|s| *s <= max);
+                assert_eq!(a.checked_add(&b).map(|s| to_u128(&s)), sum);
+                assert_eq!(a.checked_sub(&b).map(|d| to_u128(&d)), x.checked_sub(y));
+                assert_eq!(to_u128(&a.saturating_add(&b)), sum.unwrap_or(max));
+                assert_eq!(to_u128(&a.saturating_sub(&b)), x.saturating_sub(y));
+                assert_eq!(a.cmp_value(&b), x.cmp(&y));
+                assert_eq!(a.cmp(&b), x.cmp(&y));
+            }
+        }
+    }
+    #[test]
+    fn test_mixed_width_arithmetic() {
+        let a = from_u128(u64::MAX as u128, 100);
+        let one = BitVec::from_u64(1, 3);
+        assert_eq!(to_u128(&(&a + &one)), 1 << 64);
+        assert_eq!(to_u128(&(&one + &a)), 0);
+        assert!(one.checked_add(&a).is_none());
+        assert_eq!(one.wrapping_sub(&a), BitVec::from_u64(2, 3));
+        let max = !BitVec::new(100);
+        assert_eq!(max.clone().incr(), BitVec::new(100));
+        assert_eq!(from_u128(5, 70).cmp_value(&from_u128(5, 130)), Ordering::Equal);
+        assert!(from_u128(5, 70) < from_u128(5, 130));
+        assert!(from_u128(6, 3) > from_u128(5, 130));
+        let mut c = a.clone();
+        c += &one;
+        c -= one;
+        assert_eq!(c, a);
+    }
+    #[test]
+    fn test_shift_rotate() {
+        for size in [1, 64, 100, 128] {
+            let max = u128::MAX >> (128 - size);
+            let x = rand::random::<u128>() & max;
+            let a = from_u128(x, size);
+            for n in [0, 1, 5, 63, 64, 99, 127, 200] {
+                let shl = if n < 128 { (x << n) & max } else { 0 };
+                let shr = if n < 128 { x >> n } else { 0 };
+                assert_eq!(to_u128(&(&a << n)), shl, "{} << {}", size, n);
+                assert_eq!(to_u128(&(&a >> n)), shr);
+                let r = n % size;
+                let rotl = if r == 0 { x } else { (x << r | x >> (size - r)) & max };
+                assert_eq!(to_u128(&a.rotate_left(n)), rotl, "{} rotl {}", size, n);
+                assert_eq!(a.rotate_left(n).rotate_right(n), a);
+            }
+        }
+        let mut b = BitVec::new_with_vec(vec![0b10010001]);
+        b <<= 1;
+        assert_eq!(b, BitVec::new_with_vec(vec![0b00100010]));
+        b >>= 2;
+        assert_eq!(b, BitVec::new_with_vec(vec![0b00001000]));
+        assert_eq!(BitVec::new(0).rotate_left(3), BitVec::new(0));
+    }
+    #[test]
+    fn test_bitwise() {
+        for size in [0, 1, 63, 64, 65, 300] {
+            let (a, b) = (BitVec::new_with_random(size), BitVec::new_with_random(size));
+            let (and, or, xor, not) = (&a & &b, &a | &b, &a ^ &b, !&a);
+            for i in 0..size {
+                assert_eq!(and.get(i), a.get(i) && b.get(i));
+                assert_eq!(or.get(i), a.get(i) || b.get(i));
+                assert_eq!(xor.get(i), a.get(i) != b.get(i));
+                assert_eq!(not.get(i), !a.get(i));
+            }
+            assert_eq!(not.count_ones(), a.count_zeros());
+            assert_eq!(a.clone() & b.clone(), and);
+            assert_eq!(a.clone() | &b, or);
+            assert_eq!(&a ^ b.clone(), xor);
+            let mut c = a.clone();
+            c ^= &b;
+            c ^= b.clone();
+            c &= &a;
+            c |= BitVec::new(size);
+            assert_eq!(c, a);
+        }
+    }
+    #[test]
+    fn test_bitwise_mismatched_lengths() {
+        let long = BitVec::new_with_random(200);
+        let short = BitVec::new_with_random(70);
+        for (x, y) in [(&long, &short), (&short, &long)] {
+            let (and, or, xor) = (x & y, x | y, x ^ y);
+            assert_eq!((and.len(), or.len(), xor.len()), (200, 200, 200));
+            for i in 0..200 {
+                assert_eq!(and.get(i), long.get(i) && short.get(i));
+                assert_eq!(or.get(i), long.get(i) || short.get(i));
+                assert_eq!(xor.get(i), long.get(i) != short.get(i));
+            }
+            assert_eq!(and.count_ones_in(70, 200), 0);
+        }
+        let mut grown = short.clone();
+        grown |= &long;
+        assert_eq!(grown, &long | &short);
+    }
+}
+
+#[cfg(test)]
+mod iterator_test {
+    use crate::*;
+    #[test]
+    fn test_iter() {
+        for size in [0, 1, 63, 64, 65, 300] {
+            let b = BitVec::new_with_random(size);
+            let bits: Vec<bool> = (0..size).map(|i| b.get(i)).collect();
+            assert_eq!(b.iter().collect::<Vec<bool>>(), bits);
+            assert_eq!(b.iter().len(), size);
+            assert!(b.iter().rev().eq(bits.iter().rev().copied()));
+            assert_eq!((&b).into_iter().filter(|x| *x).count() as u64, b.count_ones());
+            let mut it = b.iter();
+            if size > 1 {
+                assert_eq!((it.next(), it.next_back()), (Some(bits[0]), Some(bits[size - 1])));
+                assert_eq!(it.len(), size - 2);
+            }
+        }
+    }
+    #[test]
+    fn test_iter_ones_zeros() {
+        let mut sparse = BitVec::new(1000);
+        for i in [0, 63, 64, 500, 999] {
+            sparse.set(i);
+        }
+        for b in [BitVec::new_with_random(777), BitVec::new(130), !BitVec::new(130), sparse] {
+            let ones: Vec<usize> = (0..b.len()).filter(|i| b.get(*i)).collect();
+            let zeros: Vec<usize> = (0..b.len()).filter(|i| !b.get(*i)).collect();
+            assert_eq!(b.iter_ones().collect::<Vec<usize>>(), ones);
+            assert_eq!(b.iter_zeros().collect::<Vec<usize>>(), zeros);
+            assert!(b.iter_ones().rev().eq(ones.iter().rev().copied()));
+            assert!(b.iter_zeros().rev().eq(zeros.iter().rev().copied()));
+            // Meeting in the middle yields every position exactly once.
+            let mut it = b.iter_ones();
+            let (mut front, mut back) = (vec![], vec![]);
+            loop {
+                match (it.next(), it.next_back()) {
+                    (Some(x), Some(y)) => {
+                        front.push(x);
+                        back.push(y);
+                    },
+                    (x, _) => {
+                        front.extend(x);
+                        break;
+                    },
+                }
+            }
+            front.extend(back.iter().rev());
+            assert_eq!(front, ones);
+        }
+    }
+    #[test]
+    fn test_runs() {
+        for b in [
+            BitVec::new(0),
+            BitVec::new(200),
+            BitVec::new_with_random(500),
+            !BitVec::new(130),
+        ] {
+            let runs: Vec<(bool, usize, usize)> = b.runs().collect();
+            let mut pos = 0;
+            for (k, (bit, start, len)) in runs.iter().enumerate() {
+                assert_eq!(*start, pos);
+                assert!(*len > 0);
+                assert!(k == 0 || runs[k - 1].0 != *bit);
+                assert!((pos..pos + len).all(|i| b.get(i) == *bit));
+                pos += len;
+            }
+            assert_eq!(pos, b.len());
+            assert!(b.runs().rev().eq(runs.iter().rev().copied()));
+        }
+        let mut b = BitVec::new(200);
+        for i in 10..150 {
+            b.set(i);
+        }
+        assert_eq!(
+            b.runs().collect::<Vec<_>>(),
+            [(false, 0, 10), (true, 10, 140), (false, 150, 50)]
+        );
+        let mut it = b.runs();
+        assert_eq!(it.next_back(), Some((false, 150, 50)));
+        assert_eq!(it.next(), Some((false, 0, 10)));
+        assert_eq!(it.next_back(), Some((true, 10, 140)));
+        assert_eq!(it.next(), None);
+    }
+}
+
+#[cfg(test)]
+mod growth_test {
+    use crate::*;
+    use rand::Rng;
+
+    fn check(b: &BitVec, bits: &[bool]) {
+        assert_eq!(b.len(), bits.len());
+        assert_eq!(b.num_words(), bits.len().div_ceil(64));
+        assert!(b.iter().eq(bits.iter().copied()));
+        assert_eq!(b.count_ones(), bits.iter().filter(|x| **x).count() as u64);
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut rng = rand::thread_rng();
+        let bits: Vec<bool> = (0..500).map(|_| rng.gen()).collect();
+        let mut b = BitVec::new(0);
+        for (i, bit) in bits.iter().enumerate() {
+            b.push(*bit);
+            check(&b, &bits[..=i]);
+        }
+        assert_eq!(bits.iter().copied().collect::<BitVec>(), b);
+        for i in (0..500).rev() {
+            assert_eq!(b.pop(), Some(bits[i]));
+            check(&b, &bits[..i]);
+        }
+        assert_eq!(b.pop(), None);
+    }
+    #[test]
+    fn test_resize_truncate() {
+        let mut b = BitVec::new_with_random(100);
+        let mut bits: Vec<bool> = b.iter().collect();
+        let steps = [(130, true), (130, false), (64, true), (200, false), (300, true), (3, true)];
+        for (len, fill) in steps.into_iter().chain([(0, false), (70, true)]) {
+            b.resize(len, fill);
+            bits.resize(len, fill);
+            check(&b, &bits);
+        }
+        b.truncate(65);
+        bits.truncate(65);
+        check(&b, &bits);
+        b.truncate(1000);
+        check(&b, &bits);
+    }
+    #[test]
+    fn test_extend() {
+        let mut rng = rand::thread_rng();
+        let mut b = BitVec::new(0);
+        let mut bits = vec![];
+        for _ in 0..20 {
+            let other = BitVec::new_with_random(rng.gen_range(0..150));
+            b.extend_from_bit_vec(&other);
+            bits.extend(other.iter());
+            check(&b, &bits);
+            let more: Vec<bool> = (0..rng.gen_range(0..70)).map(|_| rng.gen()).collect();
+            b.extend(&more);
+            bits.extend(&more);
+            check(&b, &bits);
+        }
+    }
+    #[test]
+    fn test_insert_remove() {
+        let mut rng = rand::thread_rng();
+        let mut b = BitVec::new(0);
+        let mut bits = vec![];
+        for round in 0..4 {
+            for _ in 0..400 {
+                if bits.is_empty() || round % 2 == 0 {
+                    let (i, bit) = (rng.gen_range(0..=bits.len()), rng.gen());
+                    b.insert(i, bit);
+                    bits.insert(i, bit);
+                } else {
+                    let i = rng.gen_range(0..bits.len());
+                    assert_eq!(b.remove(i), bits.remove(i));
+                }
+                check(&b, &bits);
+            }
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn test_remove_out_of_range() {
+        BitVec::new(64).remove(64);
+    }
+}
+
+#[cfg(test)]
+mod modification_test {
+    use crate::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_clear_flip_assign() {
+        let mut b = BitVec::new_with_random(130);
+        let mut bits: Vec<bool> = b.iter().collect();
+        for i in [0, 5, 63, 64, 100, 129] {
+            b.clear(i);
+            bits[i] = false;
+            if i + 1 < 130 {
+                b.flip(i + 1);
+                bits[i + 1] = !bits[i + 1];
+            }
+            b.assign(i / 2, i % 2 == 0);
+            bits[i / 2] = i % 2 == 0;
+            assert!(b.iter().eq(bits.iter().copied()));
+        }
+    }
+    #[test]
+    fn test_clear_flip_assign_out_of_range() {
+        for edit in [
+            |b: &mut BitVec| b.set(130),
+            |b: &mut BitVec| b.clear(130),
+            |b: &mut BitVec| b.flip(130),
+            |b: &mut BitVec| b.assign(500, true),
+            |b: &mut BitVec| b.assign(500, false),
+        ] {
+            let mut b = BitVec::new_with_random(130);
+            let res = std::panic::catch_unwind(move || edit(&mut b));
+            assert!(res.is_err());
+        }
+    }
+    #[test]
+    fn test_ranges() {
+        let mut rng = rand::thread_rng();
+        for size in [1, 64, 65, 300] {
+            let mut b = BitVec::new_with_random(size);
+            let mut bits: Vec<bool> = b.iter().collect();
+            for _ in 0..100 {
+                let left = rng.gen_range(0..=size);
+                let right = rng.gen_range(left..=size);
+                match rng.gen_range(0..3) {
+                    0 => {
+                        b.set_range(left, right);
+                        bits[left..right].fill(true);
+                    },
+                    1 => {
+                        b.clear_range(left, right);
+                        bits[left..right].fill(false);
+                    },
+                    _ => {
+                        b.flip_range(left, right);
+                        bits[left..right].iter_mut().for_each(|x| *x = !*x);
+                    },
+                }
+                assert!(b.iter().eq(bits.iter().copied()), "[{}, {})", left, right);
+            }
+            b.set_range(0, size);
+            assert_eq!(b.count_ones(), size as u64);
+            b.flip_range(0, size);
+            assert_eq!(b, BitVec::new(size));
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn test_range_out_of_bounds() {
+        BitVec::new(100).set_range(50, 101);
+    }
 }
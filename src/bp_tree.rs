@@ -0,0 +1,642 @@
+use crate::bit_vec::BitVec;
+use crate::select_support::SelectSupport;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// Number of parentheses under a leaf of the range min-max tree.
+const BLOCK_SIZE: usize = 512;
+
+/// Range min-max tree over the excess of a parentheses [`BitVec`], where an
+/// open parenthesis (a one) adds one and a close parenthesis (a zero)
+/// subtracts one. `E(k)` below is the excess of the first `k` bits.
+///
+/// Every block of `BLOCK_SIZE` bits is a leaf holding the total excess change
+/// of its bits and the minimum of `E` relative to the start of the block, both
+/// ends included. Internal nodes combine their children in a complete binary
+/// tree stored in heap order, so searches skip whole subtrees whose minimum
+/// cannot reach the target.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(in crate) struct RangeMinMax {
+    leaves: usize,
+    delta: Vec<i64>,
+    min: Vec<i64>,
+}
+
+#[inline]
+fn step(bv: &BitVec, p: usize) -> i64 {
+    if bv.get(p) {
+        1
+    } else {
+        -1
+    }
+}
+
+fn combine(left: (i64, i64), right: (i64, i64)) -> (i64, i64) {
+    (left.0 + right.0, left.1.min(left.0 + right.1))
+}
+
+impl RangeMinMax {
+    pub(in crate) fn new(bv: &BitVec) -> RangeMinMax {
+        let blocks = bv.len().div_ceil(BLOCK_SIZE);
+        let leaves = blocks.max(1).next_power_of_two();
+        let mut delta = vec![0; 2 * leaves];
+        let mut min = vec![0; 2 * leaves];
+        for b in 0..blocks {
+            let (mut e, mut m) = (0, 0);
+            for p in b * BLOCK_SIZE..((b + 1) * BLOCK_SIZE).min(bv.len()) {
+                e += step(bv, p);
+                m = m.min(e);
+            }
+            delta[leaves + b] = e;
+            min[leaves + b] = m;
+        }
+        for v in (1..leaves).rev() {
+            (delta[v], min[v]) = combine(
+                (delta[2 * v], min[2 * v]),
+                (delta[2 * v + 1], min[2 * v + 1]),
+            );
+        }
+        RangeMinMax { leaves, delta, min }
+    }
+    fn node(&self, v: usize) -> (i64, i64) {
+        (self.delta[v], self.min[v])
+    }
+    fn block_end(bv: &BitVec, b: usize) -> usize {
+        ((b + 1) * BLOCK_SIZE).min(bv.len())
+    }
+    /// Smallest `k > k0` with `E(k) == target`, given `e0 == E(k0) > target`.
+    pub(in crate) fn fwd_search(
+        &self,
+        bv: &BitVec,
+        k0: usize,
+        e0: i64,
+        target: i64,
+    ) -> Option<usize> {
+        if k0 >= bv.len() {
+            return None;
+        }
+        let mut e = e0;
+        let b = k0 / BLOCK_SIZE;
+        for p in k0..RangeMinMax::block_end(bv, b) {
+            e += step(bv, p);
+            if e == target {
+                return Some(p + 1);
+            }
+        }
+        // Climb until a right sibling reaches the target, then descend into it.
+        let mut v = self.leaves + b;
+        loop {
+            if v == 1 {
+                return None;
+            }
+            if v.is_multiple_of(2) {
+                if e + self.min[v + 1] <= target {
+                    v += 1;
+                    break;
+                }
+                e += self.delta[v + 1];
+            }
+            v /= 2;
+        }
+        while v < self.leaves {
+            if e + self.min[2 * v] <= target {
+                v *= 2;
+            } else {
+                e += self.delta[2 * v];
+                v = 2 * v + 1;
+            }
+        }
+        let b = v - self.leaves;
+        for p in b * BLOCK_SIZE..RangeMinMax::block_end(bv, b) {
+            e += step(bv, p);
+            if e == target {
+                return Some(p + 1);
+            }
+        }
+        None
+    }
+    /// Largest `k < k0` with `E(k) == target`, given `e0 == E(k0) > target`.
+    pub(in crate) fn bwd_search(
+        &self,
+        bv: &BitVec,
+        k0: usize,
+        e0: i64,
+        target: i64,
+    ) -> Option<usize> {
+        if k0 == 0 {
+            return None;
+        }
+        let mut e = e0;
+        let b = (k0 - 1) / BLOCK_SIZE;
+        for p in (b * BLOCK_SIZE..k0).rev() {
+            e -= step(bv, p);
+            if e == target {
+                return Some(p);
+            }
+        }
+        // Climb until a left sibling reaches the target, then descend into it.
+        let mut v = self.leaves + b;
+        loop {
+            if v == 1 {
+                return None;
+            }
+            if v % 2 == 1 {
+                let start = e - self.delta[v - 1];
+                if start + self.min[v - 1] <= target {
+                    v -= 1;
+                    break;
+                }
+                e = start;
+            }
+            v /= 2;
+        }
+        while v < self.leaves {
+            let start = e - self.delta[2 * v + 1];
+            if start + self.min[2 * v + 1] <= target {
+                v = 2 * v + 1;
+            } else {
+                e = start;
+                v *= 2;
+            }
+        }
+        let b = v - self.leaves;
+        for p in (b * BLOCK_SIZE..RangeMinMax::block_end(bv, b)).rev() {
+            e -= step(bv, p);
+            if e == target {
+                return Some(p);
+            }
+        }
+        None
+    }
+    /// Minimum of `E(k)` over `k` in `[a, b]`, given `ea == E(a)`.
+    pub(in crate) fn min_excess(&self, bv: &BitVec, a: usize, b: usize, ea: i64) -> i64 {
+        let (mut e, mut m) = (ea, ea);
+        let first = a / BLOCK_SIZE;
+        for p in a..b.min(RangeMinMax::block_end(bv, first)) {
+            e += step(bv, p);
+            m = m.min(e);
+        }
+        if b <= RangeMinMax::block_end(bv, first) {
+            return m;
+        }
+        // Whole blocks strictly between the first one and the one holding b - 1.
+        let last = (b - 1) / BLOCK_SIZE;
+        let (mut l, mut r) = (self.leaves + first + 1, self.leaves + last);
+        let (mut left, mut right) = ((0, 0), (0, 0));
+        while l < r {
+            if l % 2 == 1 {
+                left = combine(left, self.node(l));
+                l += 1;
+            }
+            if r % 2 == 1 {
+                r -= 1;
+                right = combine(self.node(r), right);
+            }
+            l /= 2;
+            r /= 2;
+        }
+        let (delta, min) = combine(left, right);
+        m = m.min(e + min);
+        e += delta;
+        for p in last * BLOCK_SIZE..b {
+            e += step(bv, p);
+            m = m.min(e);
+        }
+        m
+    }
+    pub(in crate) fn overhead(&self) -> usize {
+        std::mem::size_of_val(&*self.delta) + std::mem::size_of_val(&*self.min)
+    }
+}
+
+/// Ordinal tree in balanced parentheses: a depth-first traversal writes a one
+/// when it enters a node and a zero when it leaves it, `2n` bits for `n`
+/// nodes. A node is identified by the position of its open parenthesis.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct BpTree {
+    parens: SelectSupport<'static>,
+    rmm: RangeMinMax,
+}
+
+impl BpTree {
+    fn bits(&self) -> &BitVec {
+        &self.parens.r.bv
+    }
+    /// Excess of the first `k` parentheses.
+    fn excess(&self, k: usize) -> i64 {
+        2 * self.parens.r.rank1_exclusive(k) as i64 - k as i64
+    }
+    fn is_node(&self, x: usize) -> bool {
+        self.bits().get(x)
+    }
+}
+
+impl BpTree /* Public API */ {
+    /// Builds the tree from its parentheses, which must be balanced.
+    pub fn new(parens: BitVec) -> BpTree {
+        let rmm = RangeMinMax::new(&parens);
+        let tree = BpTree {
+            parens: SelectSupport::new_with_bit_vec(parens),
+            rmm,
+        };
+        // Balanced, and the root closes only at the very end.
+        let size = tree.bits().len();
+        assert!(
+            size == 0
+                || (tree.excess(size) == 0
+                    && tree.bits().get(0)
+                    && tree.rmm.min_excess(tree.bits(), 1, size - 1, 1) > 0),
+            "parentheses must be balanced and describe a single tree"
+        );
+        tree
+    }
+    /// Builds the tree with the given `(parent, child)` edges, rooted at the
+    /// only node without a parent. Children are visited in the order their
+    /// edges appear; no edges give an empty tree. Panics unless the edges form
+    /// a single tree: one root, one parent per child, every node reachable.
+    pub fn new_with_edges(edges: &[(usize, usize)]) -> BpTree {
+        let n = edges.iter().map(|(p, c)| p.max(c) + 1).max().unwrap_or(0);
+        let mut children = vec![vec![]; n];
+        let mut used = vec![false; n];
+        let mut has_parent = vec![false; n];
+        for (p, c) in edges {
+            assert!(!has_parent[*c], "node {} has more than one parent", c);
+            children[*p].push(*c);
+            has_parent[*c] = true;
+            used[*p] = true;
+            used[*c] = true;
+        }
+        let roots: Vec<usize> = (0..n).filter(|x| used[*x] && !has_parent[*x]).collect();
+        let root = match roots[..] {
+            [] if n == 0 => return BpTree::new(BitVec::new(0)),
+            [root] => root,
+            _ => panic!("edges must describe a single tree, found roots {:?}", roots),
+        };
+        // With one parent per node, a node is missed only if it sits on a cycle.
+        let mut reached = 0;
+        let mut stack = vec![root];
+        while let Some(x) = stack.pop() {
+            reached += 1;
+            stack.extend(&children[x]);
+        }
+        assert_eq!(
+            reached,
+            used.iter().filter(|u| **u).count(),
+            "edges must describe a single tree, some nodes lie on a cycle"
+        );
+        BpTree::new_with_children(root, |x| children[*x].clone())
+    }
+    /// Builds the tree rooted at `root`, `children` listing the children of a
+    /// node in order.
+    pub fn new_with_children<N, I, F>(root: N, children: F) -> BpTree
+    where
+        I: IntoIterator<Item = N>,
+        F: Fn(&N) -> I,
+    {
+        let mut bits = vec![true];
+        let mut stack = vec![children(&root).into_iter()];
+        while let Some(top) = stack.last_mut() {
+            match top.next() {
+                Some(child) => {
+                    bits.push(true);
+                    stack.push(children(&child).into_iter());
+                },
+                None => {
+                    bits.push(false);
+                    stack.pop();
+                },
+            }
+        }
+        let mut parens = BitVec::new(bits.len());
+        for (i, bit) in bits.iter().enumerate() {
+            if *bit {
+                parens.set(i);
+            }
+        }
+        BpTree::new(parens)
+    }
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.bits().len() / 2
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn root(&self) -> Option<usize> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(0)
+        }
+    }
+    /// Position of the parenthesis closing the one opened at `i`.
+    pub fn find_close(&self, i: usize) -> Option<usize> {
+        if !self.is_node(i) {
+            return None;
+        }
+        let e = self.excess(i);
+        self.rmm
+            .fwd_search(self.bits(), i + 1, e + 1, e)
+            .map(|k| k - 1)
+    }
+    /// Position of the parenthesis opening the one closed at `j`.
+    pub fn find_open(&self, j: usize) -> Option<usize> {
+        if j >= self.bits().len() || self.is_node(j) {
+            return None;
+        }
+        let e = self.excess(j);
+        self.rmm.bwd_search(self.bits(), j, e, e - 1)
+    }
+    pub fn is_leaf(&self, x: usize) -> bool {
+        self.is_node(x) && !self.bits().get(x + 1)
+    }
+    pub fn parent(&self, x: usize) -> Option<usize> {
+        if !self.is_node(x) {
+            return None;
+        }
+        let e = self.excess(x);
+        self.rmm.bwd_search(self.bits(), x, e, e - 1)
+    }
+    pub fn first_child(&self, x: usize) -> Option<usize> {
+        if self.is_node(x) && self.bits().get(x + 1) {
+            Some(x + 1)
+        } else {
+            None
+        }
+    }
+    pub fn next_sibling(&self, x: usize) -> Option<usize> {
+        let close = self.find_close(x)?;
+        if self.bits().get(close + 1) {
+            Some(close + 1)
+        } else {
+            None
+        }
+    }
+    /// Number of nodes in the subtree of `x`, `x` included.
+    pub fn subtree_size(&self, x: usize) -> usize {
+        self.find_close(x)
+            .map_or(0, |close| (close - x).div_ceil(2))
+    }
+    /// Number of edges between `x` and the root.
+    pub fn depth(&self, x: usize) -> usize {
+        self.excess(x) as usize
+    }
+    pub fn is_ancestor(&self, x: usize, y: usize) -> bool {
+        x <= y && self.find_close(x).is_some_and(|close| y < close)
+    }
+    /// Lowest common ancestor of `x` and `y`.
+    pub fn lca(&self, x: usize, y: usize) -> Option<usize> {
+        if !self.is_node(x) || !self.is_node(y) {
+            return None;
+        }
+        let (x, y) = (x.min(y), x.max(y));
+        if self.is_ancestor(x, y) {
+            return Some(x);
+        }
+        // The lowest excess between the two is reached right after leaving the
+        // child of the ancestor that holds x.
+        let m = self
+            .rmm
+            .min_excess(self.bits(), x + 1, y, self.excess(x + 1));
+        self.rmm.bwd_search(self.bits(), y, self.excess(y), m - 1)
+    }
+    /// Rank of `x` in preorder, counting from zero.
+    pub fn preorder_rank(&self, x: usize) -> usize {
+        self.parens.r.rank1_exclusive(x) as usize
+    }
+    /// Rank of `x` in postorder, counting from zero.
+    pub fn postorder_rank(&self, x: usize) -> Option<usize> {
+        self.find_close(x)
+            .map(|close| close - self.parens.r.rank1_exclusive(close) as usize)
+    }
+    /// Node of preorder rank `k`, counting from zero.
+    pub fn preorder_select(&self, k: usize) -> Option<usize> {
+        self.parens.select1(k as u64 + 1).map(|x| x as usize)
+    }
+    /// Node of postorder rank `k`, counting from zero.
+    pub fn postorder_select(&self, k: usize) -> Option<usize> {
+        let close = self.parens.select0(k as u64 + 1)? as usize;
+        self.find_open(close)
+    }
+    pub fn overhead(&self) -> usize {
+        self.parens.overhead() + self.rmm.overhead()
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<BpTree> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: BpTree = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod bp_tree_tests {
+    use crate::bit_vec::BitVec;
+    use crate::bp_tree::BpTree;
+    use rand::Rng;
+
+    /// Edges of a random tree whose node `i > 0` hangs from a node before it.
+    fn random_parents(n: usize) -> Vec<usize> {
+        let mut rng = rand::thread_rng();
+        (0..n)
+            .map(|i| {
+                if i == 0 {
+                    0
+                } else {
+                    rng.gen_range(i.saturating_sub(8)..i)
+                }
+            })
+            .collect()
+    }
+    fn preorder(children: &[Vec<usize>], x: usize, order: &mut Vec<usize>) {
+        order.push(x);
+        for c in &children[x] {
+            preorder(children, *c, order);
+        }
+    }
+    fn postorder(children: &[Vec<usize>], x: usize, order: &mut Vec<usize>) {
+        for c in &children[x] {
+            postorder(children, *c, order);
+        }
+        order.push(x);
+    }
+
+    #[test]
+    fn navigation_tests() {
+        for n in [2, 3, 10, 100, 1000, 3000] {
+            let parents = random_parents(n);
+            let edges: Vec<(usize, usize)> = (1..n).map(|i| (parents[i], i)).collect();
+            let tree = BpTree::new_with_edges(&edges);
+            assert_eq!(tree.len(), n);
+
+            let mut children = vec![vec![]; n];
+            for (p, c) in &edges {
+                children[*p].push(*c);
+            }
+            let mut pre = vec![];
+            preorder(&children, 0, &mut pre);
+            let mut post = vec![];
+            postorder(&children, 0, &mut post);
+            // Map labels to nodes through their preorder ranks.
+            let mut node = vec![0; n];
+            for (k, x) in pre.iter().enumerate() {
+                node[*x] = tree.preorder_select(k).unwrap();
+                assert_eq!(tree.preorder_rank(node[*x]), k);
+            }
+            for (k, x) in post.iter().enumerate() {
+                assert_eq!(tree.postorder_rank(node[*x]), Some(k));
+                assert_eq!(tree.postorder_select(k), Some(node[*x]));
+            }
+            // Closing parentheses are not nodes.
+            let last = tree.find_close(node[0]).unwrap();
+            assert_eq!(tree.postorder_rank(last), None);
+            let mut depth = vec![0; n];
+            let mut size = vec![1; n];
+            for x in 1..n {
+                depth[x] = depth[parents[x]] + 1;
+            }
+            for x in (1..n).rev() {
+                size[parents[x]] += size[x];
+            }
+            for x in 0..n {
+                let expected_parent = if x == 0 { None } else { Some(node[parents[x]]) };
+                assert_eq!(tree.parent(node[x]), expected_parent);
+                assert_eq!(
+                    tree.first_child(node[x]),
+                    children[x].first().map(|c| node[*c])
+                );
+                assert_eq!(tree.is_leaf(node[x]), children[x].is_empty());
+                assert_eq!(tree.depth(node[x]), depth[x]);
+                assert_eq!(tree.subtree_size(node[x]), size[x]);
+                let close = tree.find_close(node[x]).unwrap();
+                assert_eq!(tree.find_open(close), Some(node[x]));
+            }
+            for siblings in &children {
+                for (i, c) in siblings.iter().enumerate() {
+                    assert_eq!(
+                        tree.next_sibling(node[*c]),
+                        siblings.get(i + 1).map(|s| node[*s])
+                    );
+                }
+            }
+            assert_eq!(tree.next_sibling(tree.root().unwrap()), None);
+            assert_eq!(tree.preorder_select(n), None);
+        }
+    }
+    #[test]
+    fn lca_tests() {
+        let mut rng = rand::thread_rng();
+        let n = 2000;
+        let parents = random_parents(n);
+        let edges: Vec<(usize, usize)> = (1..n).map(|i| (parents[i], i)).collect();
+        let tree = BpTree::new_with_edges(&edges);
+        let mut children = vec![vec![]; n];
+        for (p, c) in &edges {
+            children[*p].push(*c);
+        }
+        let mut pre = vec![];
+        preorder(&children, 0, &mut pre);
+        let mut node = vec![0; n];
+        for (k, x) in pre.iter().enumerate() {
+            node[*x] = tree.preorder_select(k).unwrap();
+        }
+        let mut depth = vec![0; n];
+        for x in 1..n {
+            depth[x] = depth[parents[x]] + 1;
+        }
+        for _ in 0..2000 {
+            let (mut x, mut y) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            let (u, v) = (node[x], node[y]);
+            while x != y {
+                if depth[x] < depth[y] {
+                    y = parents[y];
+                } else {
+                    x = parents[x];
+                }
+            }
+            assert_eq!(tree.lca(u, v), Some(node[x]));
+        }
+    }
+    #[test]
+    fn children_constructor_tests() {
+        // A path, a star and a complete binary tree.
+        let path = BpTree::new_with_children(0, |x| if *x < 499 { vec![x + 1] } else { vec![] });
+        assert_eq!(path.len(), 500);
+        assert_eq!(path.depth(path.preorder_select(499).unwrap()), 499);
+        assert_eq!(path.lca(10, 400), Some(10));
+        let star =
+            BpTree::new_with_children(0, |x| if *x == 0 { (1..300).collect() } else { vec![] });
+        assert_eq!(star.subtree_size(0), 300);
+        assert_eq!(
+            star.lca(
+                star.preorder_select(5).unwrap(),
+                star.preorder_select(200).unwrap()
+            ),
+            Some(0)
+        );
+        let binary = BpTree::new_with_children(1_usize, |x| {
+            if *x < 512 {
+                vec![2 * x, 2 * x + 1]
+            } else {
+                vec![]
+            }
+        });
+        assert_eq!(binary.len(), 1023);
+        let leaf = binary.preorder_select(9).unwrap();
+        assert!(binary.is_leaf(leaf));
+        assert_eq!(binary.depth(leaf), 9);
+    }
+    #[test]
+    fn parentheses_tests() {
+        let parens = BitVec::new_with_vec(vec![0b11010110, 0]);
+        let tree = BpTree::new(parens.extract(0, 10));
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.find_close(0), Some(9));
+        assert_eq!(tree.find_close(1), Some(2));
+        assert_eq!(tree.next_sibling(1), Some(3));
+        assert_eq!(tree.parent(6), Some(5));
+        assert_eq!(tree.lca(1, 6), Some(0));
+        assert_eq!(tree.lca(6, 5), Some(5));
+        assert_eq!(tree.find_close(2), None);
+        assert!(BpTree::new(BitVec::new(0)).is_empty());
+    }
+    #[test]
+    #[should_panic]
+    fn unbalanced_test() {
+        BpTree::new(BitVec::new_with_vec(vec![0b11001000]).extract(0, 6));
+    }
+    #[test]
+    fn invalid_edges_tests() {
+        // Unused labels are not nodes, so label 0 does not become the root.
+        let tree = BpTree::new_with_edges(&[(1, 2), (1, 3)]);
+        assert_eq!(tree.len(), 3);
+        for edges in [
+            vec![(0, 1), (2, 3)],
+            vec![(0, 2), (1, 2)],
+            vec![(0, 1), (1, 2), (2, 1)],
+            vec![(0, 1), (2, 3), (3, 2)],
+            vec![(4, 4)],
+        ] {
+            let res = std::panic::catch_unwind(|| BpTree::new_with_edges(&edges));
+            assert!(res.is_err(), "{:?} is not a tree", edges);
+        }
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let parents = random_parents(200);
+        let edges: Vec<(usize, usize)> = (1..200).map(|i| (parents[i], i)).collect();
+        let tree = BpTree::new_with_edges(&edges);
+        tree.save("example_bp_tree.txt")?;
+        let tree2 = BpTree::load("example_bp_tree.txt".to_owned())?;
+        assert!(tree == tree2);
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use crate::bit_vec::BitVec;
+use crate::int_vec::IntVec;
+use crate::rank_support::RankSupport;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// Default number of bits per chunk.
+const DEFAULT_CHUNK_WIDTH: usize = 4;
+
+/// Directly addressable codes: every value is cut into chunks of
+/// `chunk_width` bits, least significant first, and its `l`-th chunk is
+/// stored at level `l`.
+///
+/// Each level keeps its chunks in an [`IntVec`] and a bit per chunk telling
+/// whether the value goes on. The values going on keep their order in the next
+/// level, so a value's position there is the rank of its continuation bit.
+///
+/// A value below `2^(l * chunk_width)` takes about `l * (chunk_width + 1.25)`
+/// bits, counting the rank counters, where an [`IntVec`] gives every value the
+/// width of the largest one. With one value in a hundred spread over 64 bits
+/// and the rest below 16, the default chunks take under an eighth of the
+/// space of an [`IntVec`].
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dac {
+    chunks: Vec<IntVec>,
+    more: Vec<RankSupport<'static>>,
+    chunk_width: usize,
+    len: usize,
+}
+
+impl Dac /* Public API */ {
+    pub fn new<I: IntoIterator<Item = u64>>(values: I) -> Dac {
+        Dac::new_with_chunk_width(values, DEFAULT_CHUNK_WIDTH)
+    }
+    pub fn new_with_chunk_width<I: IntoIterator<Item = u64>>(
+        values: I,
+        chunk_width: usize,
+    ) -> Dac {
+        assert!(
+            (1..=64).contains(&chunk_width),
+            "DAC chunk width must be between 1 and 64"
+        );
+        let mut level: Vec<u64> = values.into_iter().collect();
+        let len = level.len();
+        let mut chunks = vec![];
+        let mut more = vec![];
+        while !level.is_empty() {
+            let mut level_chunks = IntVec::new(chunk_width);
+            let mut level_more = BitVec::new(level.len());
+            let mut next = vec![];
+            for (i, value) in level.iter().enumerate() {
+                let rest = value.checked_shr(chunk_width as u32).unwrap_or(0);
+                level_chunks.push(value & (u64::MAX >> (64 - chunk_width)));
+                if rest > 0 {
+                    level_more.set(i);
+                    next.push(rest);
+                }
+            }
+            chunks.push(level_chunks);
+            more.push(RankSupport::new_with_index_computation(Cow::Owned(
+                level_more,
+            )));
+            level = next;
+        }
+        Dac {
+            chunks,
+            more,
+            chunk_width,
+            len,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Number of levels, the chunk count of the largest value.
+    pub fn levels(&self) -> usize {
+        self.chunks.len()
+    }
+    pub fn chunk_width(&self) -> usize {
+        self.chunk_width
+    }
+    pub fn get(&self, i: usize) -> Option<u64> {
+        if i >= self.len {
+            return None;
+        }
+        let mut value = 0;
+        let mut pos = i;
+        for level in 0..self.levels() {
+            value |= self.chunks[level].get(pos).unwrap() << (level * self.chunk_width);
+            if !self.more[level].bv.get(pos) {
+                break;
+            }
+            pos = self.more[level].rank1_exclusive(pos) as usize;
+        }
+        Some(value)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        (0..self.len).map(|i| self.get(i).unwrap())
+    }
+    pub fn overhead(&self) -> usize {
+        let chunks: usize = self.chunks.iter().map(|c| c.overhead()).sum();
+        let more: usize = self
+            .more
+            .iter()
+            .map(|m| std::mem::size_of_val(m.bv.words()) + m.overhead())
+            .sum();
+        chunks + more
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<Dac> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: Dac = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod dac_tests {
+    use crate::dac::Dac;
+    use crate::int_vec::IntVec;
+    use rand::Rng;
+
+    /// Mostly small values with a few large outliers.
+    fn skewed_values(n: usize) -> Vec<u64> {
+        let mut rng = rand::thread_rng();
+        (0..n)
+            .map(|_| {
+                if rng.gen_range(0..100) == 0 {
+                    rng.gen::<u64>() >> rng.gen_range(0..64)
+                } else {
+                    rng.gen_range(0..16)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn get_tests() {
+        for chunk_width in [1, 3, 4, 8, 64] {
+            let values = skewed_values(3000);
+            let dac = Dac::new_with_chunk_width(values.iter().copied(), chunk_width);
+            assert_eq!(dac.len(), values.len());
+            for (i, x) in values.iter().enumerate() {
+                assert_eq!(dac.get(i), Some(*x));
+            }
+            assert_eq!(dac.get(values.len()), None);
+            assert_eq!(dac.iter().collect::<Vec<u64>>(), values);
+        }
+    }
+    #[test]
+    fn edge_value_tests() {
+        let values = [0, 1, 15, 16, u64::MAX, u64::MAX >> 1, 1 << 63];
+        for chunk_width in [4, 7, 64] {
+            let dac = Dac::new_with_chunk_width(values, chunk_width);
+            for (i, x) in values.iter().enumerate() {
+                assert_eq!(dac.get(i), Some(*x));
+            }
+        }
+        let dac = Dac::new([0; 10]);
+        assert_eq!(dac.levels(), 1);
+        assert_eq!(dac.get(9), Some(0));
+        assert!(Dac::new([]).is_empty());
+    }
+    #[test]
+    fn space_tests() {
+        let values = skewed_values(100_000);
+        let dac = Dac::new(values.iter().copied());
+        let packed = IntVec::new_with_values(&values);
+        // One wide value in a hundred forces 64 bits on every IntVec slot,
+        // while the small ones here cost the DAC about 5 bits each.
+        assert!(dac.overhead() * 8 < packed.overhead());
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let values = skewed_values(500);
+        let dac = Dac::new(values.iter().copied());
+        dac.save("example_dac.txt")?;
+        let dac2 = Dac::load("example_dac.txt".to_owned())?;
+        assert_eq!(dac2.iter().collect::<Vec<u64>>(), values);
+        Ok(())
+    }
+}
@@ -0,0 +1,663 @@
+use crate::bit_vec::{BitVec, WORD_SIZE};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// Leaves this short are merged with a neighbour after a removal.
+const MIN_LEAF_LEN: usize = WORD_SIZE / 4;
+
+/// Mask of the `j < 64` lowest bits.
+fn low_mask(j: usize) -> u64 {
+    (1 << j) - 1
+}
+
+/// Node of an AVL tree whose in-order traversal lists the leaves, each holding
+/// up to 64 bits, least significant first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Node {
+    bits: u64,
+    len: usize,
+    /// Bits and ones of the whole subtree.
+    size: usize,
+    ones: usize,
+    height: u32,
+    left: Option<Box<Node>>,
+    right: Option<Box<Node>>,
+}
+
+fn size(node: &Option<Box<Node>>) -> usize {
+    node.as_ref().map_or(0, |n| n.size)
+}
+
+fn ones(node: &Option<Box<Node>>) -> usize {
+    node.as_ref().map_or(0, |n| n.ones)
+}
+
+fn height(node: &Option<Box<Node>>) -> u32 {
+    node.as_ref().map_or(0, |n| n.height)
+}
+
+impl Node {
+    fn new(bits: u64, len: usize) -> Box<Node> {
+        Box::new(Node {
+            bits,
+            len,
+            size: len,
+            ones: bits.count_ones() as usize,
+            height: 1,
+            left: None,
+            right: None,
+        })
+    }
+    fn update(&mut self) {
+        self.size = size(&self.left) + self.len + size(&self.right);
+        self.ones = ones(&self.left) + self.bits.count_ones() as usize + ones(&self.right);
+        self.height = 1 + height(&self.left).max(height(&self.right));
+    }
+    fn rotate_right(mut self: Box<Node>) -> Box<Node> {
+        let mut l = self.left.take().unwrap();
+        self.left = l.right.take();
+        self.update();
+        l.right = Some(self);
+        l.update();
+        l
+    }
+    fn rotate_left(mut self: Box<Node>) -> Box<Node> {
+        let mut r = self.right.take().unwrap();
+        self.right = r.left.take();
+        self.update();
+        r.left = Some(self);
+        r.update();
+        r
+    }
+    /// Restores the AVL invariant at `self` once its children are balanced.
+    fn balance(mut self: Box<Node>) -> Box<Node> {
+        self.update();
+        let (hl, hr) = (height(&self.left), height(&self.right));
+        if hl > hr + 1 {
+            let l = self.left.take().unwrap();
+            self.left = Some(if height(&l.right) > height(&l.left) {
+                l.rotate_left()
+            } else {
+                l
+            });
+            self.rotate_right()
+        } else if hr > hl + 1 {
+            let r = self.right.take().unwrap();
+            self.right = Some(if height(&r.left) > height(&r.right) {
+                r.rotate_right()
+            } else {
+                r
+            });
+            self.rotate_left()
+        } else {
+            self
+        }
+    }
+    /// Balanced tree over `leaves`, in order.
+    fn build(leaves: &[(u64, usize)]) -> Option<Box<Node>> {
+        if leaves.is_empty() {
+            return None;
+        }
+        let m = leaves.len() / 2;
+        let mut node = Node::new(leaves[m].0, leaves[m].1);
+        node.left = Node::build(&leaves[..m]);
+        node.right = Node::build(&leaves[m + 1..]);
+        node.update();
+        Some(node)
+    }
+    /// Adds `leaf` at position `i` of `node`, the start of a leaf or `size`.
+    fn insert_leaf(node: Option<Box<Node>>, i: usize, leaf: Box<Node>) -> Box<Node> {
+        let mut n = match node {
+            None => return leaf,
+            Some(n) => n,
+        };
+        let left_size = size(&n.left);
+        if i <= left_size {
+            n.left = Some(Node::insert_leaf(n.left.take(), i, leaf));
+        } else {
+            let right = n.right.take();
+            n.right = Some(Node::insert_leaf(right, i - left_size - n.len, leaf));
+        }
+        n.balance()
+    }
+    /// Detaches the leaf holding position `i < size` of `node`, returning the
+    /// tree left and the leaf's bits and length.
+    fn take_leaf(mut n: Box<Node>, i: usize) -> (Option<Box<Node>>, u64, usize) {
+        let left_size = size(&n.left);
+        let (bits, len);
+        if i < left_size {
+            (n.left, bits, len) = Node::take_leaf(n.left.take().unwrap(), i);
+        } else if i < left_size + n.len {
+            let (bits, len) = (n.bits, n.len);
+            return (Node::unlink(&mut n), bits, len);
+        } else {
+            let right = n.right.take().unwrap();
+            (n.right, bits, len) = Node::take_leaf(right, i - left_size - n.len);
+        }
+        (Some(n.balance()), bits, len)
+    }
+    /// Takes the children of `n` and joins them, its successor taking the
+    /// place of `n`.
+    fn unlink(n: &mut Node) -> Option<Box<Node>> {
+        match (n.left.take(), n.right.take()) {
+            (l, None) => l,
+            (l, Some(r)) => {
+                let (mut next, rest) = Node::pop_front(r);
+                next.left = l;
+                next.right = rest;
+                Some(next.balance())
+            },
+        }
+    }
+    /// Overwrites the leaf holding position `i < size` of `n` with `len` bits.
+    fn set_leaf(n: &mut Node, i: usize, bits: u64, len: usize) {
+        let left_size = size(&n.left);
+        if i < left_size {
+            Node::set_leaf(n.left.as_deref_mut().unwrap(), i, bits, len);
+        } else if i < left_size + n.len {
+            n.bits = bits;
+            n.len = len;
+        } else {
+            let right = n.right.as_deref_mut().unwrap();
+            Node::set_leaf(right, i - left_size - n.len, bits, len);
+        }
+        n.update();
+    }
+    /// Detaches the first leaf of `node`, returning it and the rest.
+    fn pop_front(mut node: Box<Node>) -> (Box<Node>, Option<Box<Node>>) {
+        match node.left.take() {
+            None => {
+                let rest = node.right.take();
+                node.update();
+                (node, rest)
+            },
+            Some(l) => {
+                let (first, rest) = Node::pop_front(l);
+                node.left = rest;
+                (first, Some(node.balance()))
+            },
+        }
+    }
+    /// Inserts `bit` before position `i <= size` of `node`.
+    fn insert(node: Option<Box<Node>>, i: usize, bit: bool) -> Box<Node> {
+        let mut n = match node {
+            None => return Node::new(bit as u64, 1),
+            Some(n) => n,
+        };
+        let left_size = size(&n.left);
+        if i < left_size {
+            n.left = Some(Node::insert(n.left.take(), i, bit));
+        } else if i <= left_size + n.len {
+            let j = i - left_size;
+            if n.len == WORD_SIZE && (j == 0 || j == WORD_SIZE) {
+                // Full leaf, edited at an end as when appending: the bit starts
+                // a leaf of its own so this one stays full.
+                let leaf = Node::new(bit as u64, 1);
+                if j == 0 {
+                    let left_size = size(&n.left);
+                    n.left = Some(Node::insert_leaf(n.left.take(), left_size, leaf));
+                } else {
+                    n.right = Some(Node::insert_leaf(n.right.take(), 0, leaf));
+                }
+                return n.balance();
+            }
+            if n.len == WORD_SIZE {
+                // Full leaf: its upper half moves to a new leaf right after it.
+                let half = WORD_SIZE / 2;
+                let upper = Node::new(n.bits >> half, half);
+                n.right = Some(Node::insert_leaf(n.right.take(), 0, upper));
+                n.bits &= low_mask(half);
+                n.len = half;
+                if j > half {
+                    let right = n.right.take();
+                    n.right = Some(Node::insert(right, j - half, bit));
+                    return n.balance();
+                }
+            }
+            let high = (n.bits >> j).checked_shl(j as u32 + 1).unwrap_or(0);
+            n.bits = (n.bits & low_mask(j)) | (bit as u64) << j | high;
+            n.len += 1;
+        } else {
+            let right = n.right.take();
+            n.right = Some(Node::insert(right, i - left_size - n.len, bit));
+        }
+        n.balance()
+    }
+    /// Removes position `i < size` of `node`, returning the tree left and the
+    /// removed bit.
+    fn remove(mut n: Box<Node>, i: usize) -> (Option<Box<Node>>, bool) {
+        let left_size = size(&n.left);
+        let bit;
+        if i < left_size {
+            let (l, b) = Node::remove(n.left.take().unwrap(), i);
+            n.left = l;
+            bit = b;
+        } else if i < left_size + n.len {
+            let j = i - left_size;
+            bit = n.bits >> j & 1 == 1;
+            let high = n.bits.checked_shr(j as u32 + 1).unwrap_or(0);
+            n.bits = (n.bits & low_mask(j)) | high << j;
+            n.len -= 1;
+            if n.len == 0 {
+                return (Node::unlink(&mut n), bit);
+            }
+        } else {
+            let (r, b) = Node::remove(n.right.take().unwrap(), i - left_size - n.len);
+            n.right = r;
+            bit = b;
+        }
+        (Some(n.balance()), bit)
+    }
+    fn leaves(&self, out: &mut Vec<(u64, usize)>) {
+        if let Some(l) = &self.left {
+            l.leaves(out);
+        }
+        out.push((self.bits, self.len));
+        if let Some(r) = &self.right {
+            r.leaves(out);
+        }
+    }
+}
+
+/// Bit vector allowing bits to be inserted and removed anywhere, with rank and
+/// select, all in `O(log n)`.
+///
+/// Bits are kept in leaves of at most one word, the nodes of an AVL tree each
+/// also counting the bits and ones below them. A full leaf is split in two
+/// halves before an insertion inside it, while one at either of its ends
+/// starts a new leaf, so appended bits fill whole words. A leaf left with
+/// `MIN_LEAF_LEN` bits or fewer by a removal is merged with a neighbour, the
+/// pair split evenly if it does not fit in a word.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DynamicBitVec {
+    root: Option<Box<Node>>,
+}
+
+impl Default for DynamicBitVec {
+    fn default() -> Self {
+        DynamicBitVec::new()
+    }
+}
+
+impl DynamicBitVec {
+    /// Leaf holding position `i < len`, and the offset of `i` in it.
+    fn leaf(&self, i: usize) -> (&Node, usize) {
+        let mut n = self.root.as_deref().unwrap();
+        let mut i = i;
+        loop {
+            let left_size = size(&n.left);
+            if i < left_size {
+                n = n.left.as_deref().unwrap();
+            } else if i < left_size + n.len {
+                return (n, i - left_size);
+            } else {
+                i -= left_size + n.len;
+                n = n.right.as_deref().unwrap();
+            }
+        }
+    }
+    /// Merges the leaf holding `i < len` with its neighbours while it is
+    /// short, splitting the bits evenly in two leaves when they overflow one.
+    fn fill_leaf(&mut self, i: usize) {
+        let mut i = i;
+        loop {
+            let (leaf, j) = self.leaf(i);
+            let (start, len) = (i - j, leaf.len);
+            if len > MIN_LEAF_LEN || len == self.len() {
+                return;
+            }
+            // The next leaf joins this one, or this one joins the previous.
+            let mid = if start + len < self.len() {
+                start + len
+            } else {
+                start
+            };
+            let (prev, j) = self.leaf(mid - 1);
+            let (first, (low, low_len)) = (mid - 1 - j, (prev.bits, prev.len));
+            let (root, high, high_len) = Node::take_leaf(self.root.take().unwrap(), mid);
+            let mut root = root.unwrap();
+            let bits = low as u128 | (high as u128) << low_len;
+            let total = low_len + high_len;
+            if total <= WORD_SIZE {
+                Node::set_leaf(&mut root, first, bits as u64, total);
+                self.root = Some(root);
+            } else {
+                let k = total / 2;
+                Node::set_leaf(&mut root, first, bits as u64 & low_mask(k), k);
+                let upper = Node::new((bits >> k) as u64, total - k);
+                self.root = Some(Node::insert_leaf(Some(root), first + k, upper));
+            }
+            if total > MIN_LEAF_LEN {
+                return;
+            }
+            i = first;
+        }
+    }
+    fn check_index(&self, i: usize) {
+        assert!(
+            i < self.len(),
+            "index {} out of range for length {}",
+            i,
+            self.len()
+        );
+    }
+}
+
+impl DynamicBitVec /* Public API */ {
+    pub fn new() -> DynamicBitVec {
+        DynamicBitVec { root: None }
+    }
+    pub fn new_with_bit_vec(bv: &BitVec) -> DynamicBitVec {
+        let leaves: Vec<(u64, usize)> = (0..bv.len())
+            .step_by(WORD_SIZE)
+            .map(|pos| {
+                let len = WORD_SIZE.min(bv.len() - pos);
+                (
+                    bv.get_bits(pos, len).reverse_bits() >> (WORD_SIZE - len),
+                    len,
+                )
+            })
+            .collect();
+        DynamicBitVec {
+            root: Node::build(&leaves),
+        }
+    }
+    pub fn to_bit_vec(&self) -> BitVec {
+        let mut leaves = vec![];
+        if let Some(root) = &self.root {
+            root.leaves(&mut leaves);
+        }
+        let mut bv = BitVec::new(self.len());
+        let mut pos = 0;
+        for (bits, len) in leaves {
+            bv.set_bits(pos, len, bits.reverse_bits() >> (WORD_SIZE - len));
+            pos += len;
+        }
+        bv
+    }
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+    pub fn count_ones(&self) -> u64 {
+        ones(&self.root) as u64
+    }
+    pub fn get(&self, i: usize) -> bool {
+        if i >= self.len() {
+            return false;
+        }
+        let (leaf, j) = self.leaf(i);
+        leaf.bits >> j & 1 == 1
+    }
+    /// Overwrites the bit at `i < len`.
+    pub fn set(&mut self, i: usize, bit: bool) {
+        self.check_index(i);
+        let delta = bit as isize - self.get(i) as isize;
+        let mut n = self.root.as_deref_mut().unwrap();
+        let mut i = i;
+        loop {
+            n.ones = (n.ones as isize + delta) as usize;
+            let left_size = size(&n.left);
+            if i < left_size {
+                n = n.left.as_deref_mut().unwrap();
+            } else if i < left_size + n.len {
+                let j = i - left_size;
+                n.bits = (n.bits & !(1 << j)) | (bit as u64) << j;
+                return;
+            } else {
+                i -= left_size + n.len;
+                n = n.right.as_deref_mut().unwrap();
+            }
+        }
+    }
+    /// Inserts `bit` before position `i <= len`.
+    pub fn insert(&mut self, i: usize, bit: bool) {
+        assert!(
+            i <= self.len(),
+            "insertion index {} out of range for length {}",
+            i,
+            self.len()
+        );
+        self.root = Some(Node::insert(self.root.take(), i, bit));
+    }
+    pub fn push(&mut self, bit: bool) {
+        self.insert(self.len(), bit);
+    }
+    /// Removes the bit at `i < len` and returns it.
+    pub fn remove(&mut self, i: usize) -> bool {
+        self.check_index(i);
+        let (root, bit) = Node::remove(self.root.take().unwrap(), i);
+        self.root = root;
+        // The leaf the bit left, unless it emptied, holds `i - 1` or `i`.
+        for p in [i.saturating_sub(1), i] {
+            if p < self.len() {
+                self.fill_leaf(p);
+            }
+        }
+        bit
+    }
+    /// Number of ones in `[0, i]`.
+    pub fn rank1(&self, i: u64) -> u64 {
+        let mut i = (i as usize).min(self.len().saturating_sub(1));
+        let mut res = 0;
+        let mut node = self.root.as_deref();
+        while let Some(n) = node {
+            let left_size = size(&n.left);
+            if i < left_size {
+                node = n.left.as_deref();
+            } else if i < left_size + n.len {
+                let j = i - left_size;
+                let mask = u64::MAX >> (WORD_SIZE - 1 - j);
+                return (res + ones(&n.left)) as u64 + (n.bits & mask).count_ones() as u64;
+            } else {
+                res += ones(&n.left) + n.bits.count_ones() as usize;
+                i -= left_size + n.len;
+                node = n.right.as_deref();
+            }
+        }
+        0
+    }
+    /// Number of zeros in `[0, i]`.
+    pub fn rank0(&self, i: u64) -> u64 {
+        i.saturating_add(1).min(self.len() as u64) - self.rank1(i)
+    }
+    /// Position of the `i`-th one, counting from one.
+    pub fn select1(&self, i: u64) -> Option<u64> {
+        if i == 0 || i > self.count_ones() {
+            return None;
+        }
+        let mut k = i as usize - 1;
+        let mut pos = 0;
+        let mut n = self.root.as_deref().unwrap();
+        loop {
+            let left_ones = ones(&n.left);
+            let leaf_ones = n.bits.count_ones() as usize;
+            if k < left_ones {
+                n = n.left.as_deref().unwrap();
+            } else if k < left_ones + leaf_ones {
+                let mut bits = n.bits;
+                for _ in 0..k - left_ones {
+                    bits &= bits - 1;
+                }
+                let j = bits.trailing_zeros() as usize;
+                return Some((pos + size(&n.left) + j) as u64);
+            } else {
+                k -= left_ones + leaf_ones;
+                pos += size(&n.left) + n.len;
+                n = n.right.as_deref().unwrap();
+            }
+        }
+    }
+    pub fn overhead(&self) -> usize {
+        fn nodes(n: &Option<Box<Node>>) -> usize {
+            n.as_ref()
+                .map_or(0, |n| 1 + nodes(&n.left) + nodes(&n.right))
+        }
+        nodes(&self.root) * std::mem::size_of::<Node>()
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<DynamicBitVec> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: DynamicBitVec = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod dynamic_bit_vec_tests {
+    use crate::bit_vec::BitVec;
+    use crate::dynamic_bit_vec::{height, DynamicBitVec, Node};
+    use crate::rank_support::RankSupport;
+    use crate::select_support::SelectSupport;
+    use rand::Rng;
+    use std::borrow::Cow;
+
+    fn check_balanced(n: &Option<Box<Node>>) {
+        if let Some(n) = n {
+            assert!(height(&n.left).abs_diff(height(&n.right)) <= 1);
+            assert!(n.len > 0 && n.len <= 64);
+            check_balanced(&n.left);
+            check_balanced(&n.right);
+        }
+    }
+    fn leaf_lens(dbv: &DynamicBitVec) -> Vec<usize> {
+        let mut leaves = vec![];
+        if let Some(root) = &dbv.root {
+            root.leaves(&mut leaves);
+        }
+        leaves.iter().map(|(_, len)| *len).collect()
+    }
+    /// Checks `dbv` against the same bits held in a `Vec<bool>`.
+    fn check_against(dbv: &DynamicBitVec, bits: &[bool]) {
+        check_balanced(&dbv.root);
+        assert_eq!(dbv.len(), bits.len());
+        let mut ones = 0;
+        for (i, b) in bits.iter().enumerate() {
+            assert_eq!(dbv.get(i), *b);
+            ones += *b as u64;
+            assert_eq!(dbv.rank1(i as u64), ones);
+            if *b {
+                assert_eq!(dbv.select1(ones), Some(i as u64));
+            }
+        }
+        assert_eq!(dbv.count_ones(), ones);
+        assert_eq!(dbv.select1(ones + 1), None);
+    }
+
+    #[test]
+    fn edit_tests() {
+        let mut rng = rand::thread_rng();
+        let mut dbv = DynamicBitVec::new();
+        let mut bits = vec![];
+        for round in 0..6 {
+            for _ in 0..3000 {
+                let op = rng.gen_range(0..10);
+                if bits.is_empty() || op < 5 - round % 2 * 3 {
+                    let (i, b) = (rng.gen_range(0..=bits.len()), rng.gen_bool(0.4));
+                    dbv.insert(i, b);
+                    bits.insert(i, b);
+                } else if op < 8 {
+                    let i = rng.gen_range(0..bits.len());
+                    assert_eq!(dbv.remove(i), bits.remove(i));
+                } else {
+                    let (i, b) = (rng.gen_range(0..bits.len()), rng.gen());
+                    dbv.set(i, b);
+                    bits[i] = b;
+                }
+            }
+            check_against(&dbv, &bits);
+        }
+        while !bits.is_empty() {
+            assert_eq!(dbv.remove(0), bits.remove(0));
+        }
+        assert!(dbv.is_empty());
+    }
+    #[test]
+    fn append_tests() {
+        let mut dbv = DynamicBitVec::new();
+        let mut bits = vec![];
+        for i in 0..5000 {
+            dbv.push(i % 3 == 0);
+            bits.push(i % 3 == 0);
+        }
+        check_against(&dbv, &bits);
+        // Appending fills every leaf but the last.
+        assert_eq!(leaf_lens(&dbv).len(), 5000_usize.div_ceil(64));
+        for i in 0..2000 {
+            dbv.insert(0, i % 2 == 0);
+            bits.insert(0, i % 2 == 0);
+        }
+        check_against(&dbv, &bits);
+        assert_eq!(
+            leaf_lens(&dbv).len(),
+            5000_usize.div_ceil(64) + 2000_usize.div_ceil(64)
+        );
+    }
+    #[test]
+    fn leaf_fill_tests() {
+        let mut rng = rand::thread_rng();
+        let bv = BitVec::new_with_random(64 * 500);
+        let mut dbv = DynamicBitVec::new_with_bit_vec(&bv);
+        let mut bits: Vec<bool> = bv.iter().collect();
+        while bits.len() > 2000 {
+            let i = rng.gen_range(0..bits.len());
+            assert_eq!(dbv.remove(i), bits.remove(i));
+        }
+        check_against(&dbv, &bits);
+        // Removals merge short leaves, so none is left with a quarter word.
+        let lens = leaf_lens(&dbv);
+        assert!(lens.iter().all(|len| *len > 16), "{:?}", lens);
+        assert!(lens.len() * 16 < bits.len());
+        while bits.len() > 1 {
+            let i = rng.gen_range(0..bits.len());
+            assert_eq!(dbv.remove(i), bits.remove(i));
+            assert!(leaf_lens(&dbv).len() == 1 || leaf_lens(&dbv).iter().all(|len| *len > 16));
+        }
+        check_against(&dbv, &bits);
+    }
+    #[test]
+    fn bit_vec_tests() {
+        for size in [0, 1, 63, 64, 65, 1000, 4321] {
+            let bv = BitVec::new_with_random(size);
+            let dbv = DynamicBitVec::new_with_bit_vec(&bv);
+            let r = RankSupport::new(&bv);
+            let s = SelectSupport::new(Cow::Borrowed(&r));
+            for i in 0..size {
+                assert_eq!(dbv.get(i), bv.get(i));
+                assert_eq!(dbv.rank1(i as u64), r.rank1(i as u64));
+                assert_eq!(dbv.rank0(i as u64), r.rank0(i as u64));
+            }
+            for k in 0..=bv.count_ones() + 1 {
+                assert_eq!(dbv.select1(k), s.select1(k));
+            }
+            assert_eq!(dbv.rank1(size as u64 + 5), bv.count_ones());
+            assert_eq!(dbv.rank0(u64::MAX), bv.count_zeros());
+            assert_eq!(dbv.to_bit_vec(), bv);
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn out_of_range_test() {
+        let mut dbv = DynamicBitVec::new_with_bit_vec(&BitVec::new(10));
+        dbv.remove(10);
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let mut dbv = DynamicBitVec::new_with_bit_vec(&BitVec::new_with_random(300));
+        dbv.insert(150, true);
+        dbv.save("example_dynamic_bit_vec.txt")?;
+        let dbv2 = DynamicBitVec::load("example_dynamic_bit_vec.txt".to_owned())?;
+        assert_eq!(dbv, dbv2);
+        Ok(())
+    }
+}
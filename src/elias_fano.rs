@@ -0,0 +1,262 @@
+use crate::bit_vec::BitVec;
+use crate::select_support::SelectSupport;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// Elias-Fano encoding of a non-decreasing sequence of `n` integers below
+/// `universe`, taking about `n * (2 + log(universe / n))` bits.
+///
+/// Every value is split into `low_width` low bits, packed back to back in
+/// `lower`, and a high part stored in unary in `upper`: the `k`-th value sets
+/// bit `(value >> low_width) + k`. Zeros in `upper` therefore separate the
+/// buckets of values sharing a high part.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct EliasFano {
+    upper: SelectSupport<'static>,
+    lower: BitVec,
+    low_width: usize,
+    len: usize,
+    universe: u64,
+}
+
+impl EliasFano {
+    fn low(&self, k: usize) -> u64 {
+        self.lower.get_bits(k * self.low_width, self.low_width)
+    }
+    fn upper_bits(&self) -> &BitVec {
+        &self.upper.r.bv
+    }
+    /// Number of values smaller than `x`.
+    fn rank_exclusive(&self, x: u64) -> usize {
+        if x == 0 {
+            0
+        } else {
+            self.rank1(x - 1) as usize
+        }
+    }
+}
+
+impl EliasFano /* Public API */ {
+    /// Builds the sequence over the smallest universe holding `positions`.
+    pub fn new(positions: &[u64]) -> EliasFano {
+        let universe = positions.last().map_or(0, |last| {
+            last.checked_add(1)
+                .expect("Elias-Fano positions must be below u64::MAX")
+        });
+        EliasFano::new_with_universe(positions, universe)
+    }
+    pub fn new_with_universe(positions: &[u64], universe: u64) -> EliasFano {
+        assert!(
+            positions.windows(2).all(|w| w[0] <= w[1]),
+            "Elias-Fano positions must be sorted"
+        );
+        assert!(
+            positions.last().is_none_or(|last| *last < universe),
+            "Elias-Fano positions must be below the universe"
+        );
+        let len = positions.len();
+        let low_width = if len == 0 || universe <= len as u64 {
+            0
+        } else {
+            (universe / len as u64).ilog2() as usize
+        };
+        let mut upper = BitVec::new(len + (universe >> low_width) as usize + 1);
+        let mut lower = BitVec::new(len * low_width);
+        for (k, x) in positions.iter().enumerate() {
+            upper.set((x >> low_width) as usize + k);
+            lower.set_bits(k * low_width, low_width, *x);
+        }
+        let upper = SelectSupport::new_with_bit_vec(upper);
+        EliasFano {
+            upper,
+            lower,
+            low_width,
+            len,
+            universe,
+        }
+    }
+    /// Builds the sequence of set positions of `bv`.
+    pub fn new_with_bit_vec(bv: &BitVec) -> EliasFano {
+        let positions: Vec<u64> = bv.iter_ones().map(|i| i as u64).collect();
+        EliasFano::new_with_universe(&positions, bv.len() as u64)
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn universe(&self) -> u64 {
+        self.universe
+    }
+    /// The `k`-th value, counting from zero.
+    pub fn access(&self, k: usize) -> Option<u64> {
+        if k >= self.len {
+            return None;
+        }
+        let high = self.upper.select1(k as u64 + 1)? - k as u64;
+        Some((high << self.low_width) | self.low(k))
+    }
+    /// Number of values in `[0, i]`.
+    pub fn rank1(&self, i: u64) -> u64 {
+        if i >= self.universe {
+            return self.len as u64;
+        }
+        let high = i >> self.low_width;
+        let low = i & ((1 << self.low_width) - 1);
+        // The values of bucket `high` lie between its two delimiting zeros, and
+        // their low parts are sorted, so a binary search finds the rank.
+        let bucket_end = |h: u64| (self.upper.select0(h + 1).unwrap() - h) as usize;
+        let (mut lo, mut hi) = match high {
+            0 => (0, bucket_end(0)),
+            h => (bucket_end(h - 1), bucket_end(h)),
+        };
+        while lo < hi {
+            let m = (lo + hi) / 2;
+            if self.low(m) <= low {
+                lo = m + 1;
+            } else {
+                hi = m;
+            }
+        }
+        lo as u64
+    }
+    /// The `i`-th value, counting from one like [`SelectSupport::select1`].
+    pub fn select1(&self, i: u64) -> Option<u64> {
+        if i == 0 {
+            None
+        } else {
+            self.access(i as usize - 1)
+        }
+    }
+    /// Smallest value greater than or equal to `x`.
+    pub fn successor(&self, x: u64) -> Option<u64> {
+        self.access(self.rank_exclusive(x))
+    }
+    /// Largest value less than or equal to `x`.
+    pub fn predecessor(&self, x: u64) -> Option<u64> {
+        match self.rank1(x) {
+            0 => None,
+            r => self.access(r as usize - 1),
+        }
+    }
+    /// Total bytes used by the upper bits, their select index and the lower bits.
+    pub fn overhead(&self) -> usize {
+        std::mem::size_of_val(self.upper_bits().words())
+            + self.upper.overhead()
+            + std::mem::size_of_val(self.lower.words())
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<EliasFano> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: EliasFano = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod elias_fano_tests {
+    use crate::bit_vec::BitVec;
+    use crate::elias_fano::EliasFano;
+    use crate::rank_support::RankSupport;
+    use rand::Rng;
+
+    fn random_positions(n: usize, universe: u64) -> Vec<u64> {
+        let mut rng = rand::thread_rng();
+        let mut positions: Vec<u64> = (0..n).map(|_| rng.gen_range(0..universe)).collect();
+        positions.sort();
+        positions
+    }
+
+    #[test]
+    fn access_select_tests() {
+        for (n, universe) in [(0, 10), (1, 1), (10, 10), (100, 1000), (500, 1 << 20)] {
+            let positions = random_positions(n, universe);
+            let ef = EliasFano::new_with_universe(&positions, universe);
+            assert_eq!(ef.len(), n);
+            for (k, x) in positions.iter().enumerate() {
+                assert_eq!(ef.access(k), Some(*x));
+                assert_eq!(ef.select1(k as u64 + 1), Some(*x));
+            }
+            assert_eq!(ef.access(n), None);
+            assert_eq!(ef.select1(0), None);
+        }
+    }
+    #[test]
+    fn rank_tests() {
+        for size in [1, 64, 700, 3000] {
+            let b = BitVec::new_with_random(size);
+            let r = RankSupport::new(&b);
+            let ef = EliasFano::new_with_bit_vec(&b);
+            for j in 0..size as u64 + 5 {
+                assert_eq!(ef.rank1(j), r.rank1(j), "size {} point {}", size, j);
+            }
+        }
+    }
+    #[test]
+    fn successor_predecessor_tests() {
+        let positions = random_positions(300, 5000);
+        let ef = EliasFano::new_with_universe(&positions, 5000);
+        for x in 0..5100 {
+            let succ = positions.iter().find(|&&p| p >= x).copied();
+            let pred = positions.iter().rev().find(|&&p| p <= x).copied();
+            assert_eq!(ef.successor(x), succ, "successor of {}", x);
+            assert_eq!(ef.predecessor(x), pred, "predecessor of {}", x);
+        }
+    }
+    #[test]
+    fn duplicate_tests() {
+        let positions = vec![2, 2, 2, 7, 9, 9];
+        let ef = EliasFano::new(&positions);
+        assert_eq!(ef.rank1(1), 0);
+        assert_eq!(ef.rank1(2), 3);
+        assert_eq!(ef.rank1(8), 4);
+        assert_eq!(ef.rank1(9), 6);
+        assert_eq!(ef.successor(3), Some(7));
+    }
+    #[test]
+    fn clustered_rank_tests() {
+        // Most values share one bucket, which rank must not walk one by one.
+        let mut positions: Vec<u64> = (0..5000).map(|k| 1000 + k / 3).collect();
+        positions.extend([1 << 30, (1 << 30) + 5]);
+        let ef = EliasFano::new(&positions);
+        for x in [0, 999, 1000, 1001, 1500, 2666, 2667, 1 << 29, 1 << 30, (1 << 30) + 5] {
+            let expected = positions.iter().filter(|&&p| p <= x).count() as u64;
+            assert_eq!(ef.rank1(x), expected, "rank of {}", x);
+        }
+        assert_eq!(ef.successor(2667), Some(1 << 30));
+        assert_eq!(ef.predecessor(2667), Some(2666));
+    }
+    #[test]
+    fn space_tests() {
+        let positions = random_positions(1000, 100_000_000);
+        let ef = EliasFano::new_with_universe(&positions, 100_000_000);
+        // About 2 + log(10^5) = 19 bits per value, plus the select index.
+        assert!(ef.overhead() < 8 * 1024, "overhead {}", ef.overhead());
+    }
+    #[test]
+    #[should_panic(expected = "below u64::MAX")]
+    fn max_position_test() {
+        EliasFano::new(&[3, u64::MAX]);
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let positions = random_positions(200, 10_000);
+        let ef = EliasFano::new_with_universe(&positions, 10_000);
+        ef.save("example_elias_fano.txt")?;
+        let ef2 = EliasFano::load("example_elias_fano.txt".to_owned())?;
+        for (k, x) in positions.iter().enumerate() {
+            assert_eq!(ef2.access(k), Some(*x));
+        }
+        assert_eq!(ef2.rank1(5000), ef.rank1(5000));
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use crate::bit_vec::BitVec;
+use crate::rank_support::RankSupport;
+use crate::suffix;
+use crate::wavelet_matrix::WaveletMatrix;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// Default distance between two sampled text positions.
+const DEFAULT_SAMPLE_RATE: usize = 32;
+
+/// FM-index over a byte text.
+///
+/// The text is indexed with a sentinel appended, smaller than every byte: the
+/// BWT is stored over the alphabet `[0, 257)` (sentinel `0`, byte `b` as
+/// `b + 1`) in a [`WaveletMatrix`], which answers the per-symbol occurrence
+/// ranks of backward search. Every `sample_rate`-th text position keeps its
+/// suffix array row (for `locate`) and its inverse (for `extract`).
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct FmIndex {
+    bwt: WaveletMatrix,
+    c: Vec<usize>,
+    sample_rate: usize,
+    sampled_rows: RankSupport<'static>,
+    sa_samples: Vec<usize>,
+    isa_samples: Vec<usize>,
+}
+
+impl FmIndex {
+    /// Length of the indexed text, sentinel included.
+    fn rows(&self) -> usize {
+        self.bwt.len()
+    }
+    /// Row of the suffix one position to the left of the suffix at `row`.
+    fn lf(&self, row: usize) -> usize {
+        let c = self.bwt.access(row).unwrap();
+        self.c[c as usize] + self.bwt.rank_exclusive(c, row)
+    }
+    /// Rows `[sp, ep)` of the suffixes starting with `pattern`.
+    fn backward_search(&self, pattern: &[u8]) -> (usize, usize) {
+        let (mut sp, mut ep) = (0, self.rows());
+        for b in pattern.iter().rev() {
+            let c = *b as u64 + 1;
+            sp = self.c[c as usize] + self.bwt.rank_exclusive(c, sp);
+            ep = self.c[c as usize] + self.bwt.rank_exclusive(c, ep);
+            if sp >= ep {
+                return (0, 0);
+            }
+        }
+        (sp, ep)
+    }
+    /// Text position of the suffix at `row`.
+    fn locate_row(&self, row: usize) -> usize {
+        let mut row = row;
+        let mut steps = 0;
+        while !self.sampled_rows.bv.get(row) {
+            row = self.lf(row);
+            steps += 1;
+        }
+        let sample = self.sampled_rows.rank1_exclusive(row) as usize;
+        self.sa_samples[sample] + steps
+    }
+}
+
+impl FmIndex /* Public API */ {
+    pub fn new(text: &[u8]) -> FmIndex {
+        FmIndex::new_with_sample_rate(text, DEFAULT_SAMPLE_RATE)
+    }
+    pub fn new_with_sample_rate(text: &[u8], sample_rate: usize) -> FmIndex {
+        assert!(sample_rate > 0, "FM-index sample rate must be positive");
+        let mut symbols: Vec<u16> = text.iter().map(|b| *b as u16 + 1).collect();
+        symbols.push(0);
+        let n = symbols.len();
+        let sa = suffix::suffix_array_int(&symbols);
+        let bwt: Vec<u16> = sa.iter().map(|i| symbols[(i + n - 1) % n]).collect();
+
+        let mut c = vec![0; 258];
+        for s in &symbols {
+            c[*s as usize + 1] += 1;
+        }
+        for i in 1..c.len() {
+            c[i] += c[i - 1];
+        }
+
+        let mut sampled_rows = BitVec::new(n);
+        let mut sa_samples = vec![];
+        let mut isa_samples = vec![0; n.div_ceil(sample_rate)];
+        for (row, pos) in sa.iter().enumerate() {
+            if pos % sample_rate == 0 {
+                sampled_rows.set(row);
+                sa_samples.push(*pos);
+                isa_samples[pos / sample_rate] = row;
+            }
+        }
+        FmIndex {
+            bwt: WaveletMatrix::new(&bwt),
+            c,
+            sample_rate,
+            sampled_rows: RankSupport::new_with_index_computation(Cow::Owned(sampled_rows)),
+            sa_samples,
+            isa_samples,
+        }
+    }
+    /// Length of the indexed text.
+    pub fn len(&self) -> usize {
+        self.rows() - 1
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Number of occurrences of `pattern` in the text.
+    pub fn count(&self, pattern: &[u8]) -> usize {
+        let (sp, ep) = self.backward_search(pattern);
+        ep - sp
+    }
+    /// Sorted starting positions of the occurrences of `pattern`.
+    pub fn locate(&self, pattern: &[u8]) -> Vec<usize> {
+        let (sp, ep) = self.backward_search(pattern);
+        let mut positions: Vec<usize> = (sp..ep).map(|row| self.locate_row(row)).collect();
+        positions.sort();
+        positions
+    }
+    /// The text in `[start, start + len)`, clipped to the end of the text.
+    pub fn extract(&self, start: usize, len: usize) -> Vec<u8> {
+        let end = (start + len).min(self.len());
+        if start >= end {
+            return vec![];
+        }
+        // Walk backwards from the first sampled position at or after `end`,
+        // the sentinel (row 0) standing in past the last sample.
+        let sample = end.div_ceil(self.sample_rate);
+        let (mut pos, mut row) = match self.isa_samples.get(sample) {
+            Some(row) => (sample * self.sample_rate, *row),
+            None => (self.len(), 0),
+        };
+        let mut res = Vec::with_capacity(end - start);
+        while pos > start {
+            let c = self.bwt.access(row).unwrap();
+            pos -= 1;
+            if pos < end {
+                res.push((c - 1) as u8);
+            }
+            row = self.lf(row);
+        }
+        res.reverse();
+        res
+    }
+    pub fn overhead(&self) -> usize {
+        self.bwt.overhead()
+            + std::mem::size_of_val(&*self.c)
+            + std::mem::size_of_val(self.sampled_rows.bv.words())
+            + self.sampled_rows.overhead()
+            + std::mem::size_of_val(&*self.sa_samples)
+            + std::mem::size_of_val(&*self.isa_samples)
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<FmIndex> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: FmIndex = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod fm_index_tests {
+    use crate::fm_index::FmIndex;
+    use rand::Rng;
+
+    fn random_dna(n: usize) -> Vec<u8> {
+        let mut rng = rand::thread_rng();
+        (0..n).map(|_| b"ACGT"[rng.gen_range(0..4)]).collect()
+    }
+    fn naive_locate(text: &[u8], pattern: &[u8]) -> Vec<usize> {
+        if pattern.is_empty() {
+            return (0..=text.len()).collect();
+        }
+        text.windows(pattern.len())
+            .enumerate()
+            .filter(|(_, w)| *w == pattern)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn count_locate_tests() {
+        for sample_rate in [1, 4, 32] {
+            let text = random_dna(2000);
+            let fm = FmIndex::new_with_sample_rate(&text, sample_rate);
+            for len in [1, 2, 5, 9] {
+                for start in (0..text.len() - len).step_by(97) {
+                    let pattern = &text[start..start + len];
+                    let expected = naive_locate(&text, pattern);
+                    assert_eq!(fm.count(pattern), expected.len());
+                    assert_eq!(fm.locate(pattern), expected);
+                }
+            }
+            assert_eq!(fm.count(b"N"), 0);
+            assert_eq!(fm.locate(b"ACGTN"), Vec::<usize>::new());
+        }
+    }
+    #[test]
+    fn byte_text_tests() {
+        let text = b"mississippi\x00\xffmississippi";
+        let fm = FmIndex::new_with_sample_rate(text, 3);
+        assert_eq!(fm.len(), text.len());
+        assert_eq!(fm.count(b"ssi"), 4);
+        assert_eq!(fm.locate(b"issi"), vec![1, 4, 14, 17]);
+        assert_eq!(fm.locate(b"\x00\xff"), vec![11]);
+        assert_eq!(fm.extract(0, text.len()), text.to_vec());
+    }
+    #[test]
+    fn extract_tests() {
+        let text = random_dna(500);
+        for sample_rate in [1, 7, 64, 1000] {
+            let fm = FmIndex::new_with_sample_rate(&text, sample_rate);
+            for (start, len) in [(0, 500), (0, 1), (13, 40), (499, 1), (450, 100), (500, 3)] {
+                let end = (start + len).min(text.len());
+                assert_eq!(fm.extract(start, len), text[start.min(end)..end].to_vec());
+            }
+        }
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let text = random_dna(300);
+        let fm = FmIndex::new(&text);
+        fm.save("example_fm_index.txt")?;
+        let fm2 = FmIndex::load("example_fm_index.txt".to_owned())?;
+        let pattern = &text[10..14];
+        assert_eq!(fm2.locate(pattern), naive_locate(&text, pattern));
+        assert_eq!(fm2.extract(0, 300), text);
+        Ok(())
+    }
+}
@@ -0,0 +1,737 @@
+use crate::bit_vec::{bit_mask, prefix_mask, select_in_word, BitVec, WORD_SIZE};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// Bits of a value addressing its chunk's container.
+const CHUNK_BITS: u32 = 16;
+/// Words of a bitmap container, in the bit order of a [`BitVec`].
+const CHUNK_WORDS: usize = (1 << CHUNK_BITS) / WORD_SIZE;
+/// Largest array container; one more value takes as much room as a bitmap.
+const MAX_ARRAY_LEN: usize = 4096;
+/// Largest run container, for the same reason.
+const MAX_RUNS: usize = 2048;
+
+/// Set of the low 16 bits of the values of one chunk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Container {
+    /// Sorted values.
+    Array(Vec<u16>),
+    /// One bit per value, and the number of values.
+    Bitmap(Vec<u64>, u32),
+    /// Sorted, non-adjacent runs `[start, end]`.
+    Run(Vec<(u16, u16)>),
+}
+
+impl Container {
+    /// The smallest container holding the values set in `words`, `None` when
+    /// there are none.
+    fn new_with_words(words: Vec<u64>) -> Option<Container> {
+        let len: u32 = words.iter().map(|w| w.count_ones()).sum();
+        if len == 0 {
+            return None;
+        }
+        // A run starts at every one whose previous bit is a zero.
+        let mut runs = 0;
+        let mut carry = 0;
+        for w in &words {
+            runs += (w & !(w >> 1 | carry)).count_ones() as usize;
+            carry = w << (WORD_SIZE - 1);
+        }
+        let array_size = 2 * len as usize;
+        let bitmap_size = 8 * CHUNK_WORDS;
+        let run_size = 4 * runs;
+        let values = || Container::ones(&words);
+        Some(if run_size < array_size.min(bitmap_size) {
+            let mut res: Vec<(u16, u16)> = Vec::with_capacity(runs);
+            for x in values() {
+                match res.last_mut() {
+                    Some((_, end)) if *end as u32 + 1 == x as u32 => *end = x,
+                    _ => res.push((x, x)),
+                }
+            }
+            Container::Run(res)
+        } else if len as usize <= MAX_ARRAY_LEN {
+            Container::Array(values().collect())
+        } else {
+            Container::Bitmap(words, len)
+        })
+    }
+    fn words(&self) -> Vec<u64> {
+        match self {
+            Container::Bitmap(words, _) => words.clone(),
+            _ => {
+                let mut words = vec![0; CHUNK_WORDS];
+                for x in self.iter() {
+                    words[x as usize / WORD_SIZE] |= bit_mask(x as usize);
+                }
+                words
+            },
+        }
+    }
+    /// Positions of the ones of bitmap `words`, in increasing order.
+    fn ones(words: &[u64]) -> impl Iterator<Item = u16> + '_ {
+        words.iter().enumerate().flat_map(|(i, w)| {
+            (0..WORD_SIZE)
+                .filter(move |b| w & bit_mask(*b) != 0)
+                .map(move |b| (i * WORD_SIZE + b) as u16)
+        })
+    }
+    /// Number of values, in time linear in the runs of a run container;
+    /// [`HybridBitmap`] keeps these counts instead of asking.
+    fn len(&self) -> u32 {
+        match self {
+            Container::Array(values) => values.len() as u32,
+            Container::Bitmap(_, len) => *len,
+            Container::Run(runs) => runs.iter().map(|(s, e)| (e - s) as u32 + 1).sum(),
+        }
+    }
+    fn is_empty(&self) -> bool {
+        match self {
+            Container::Array(values) => values.is_empty(),
+            Container::Bitmap(_, len) => *len == 0,
+            Container::Run(runs) => runs.is_empty(),
+        }
+    }
+    /// Index of the last run starting at or before `x`.
+    fn find_run(runs: &[(u16, u16)], x: u16) -> Option<usize> {
+        runs.partition_point(|(s, _)| *s <= x).checked_sub(1)
+    }
+    fn contains(&self, x: u16) -> bool {
+        match self {
+            Container::Array(values) => values.binary_search(&x).is_ok(),
+            Container::Bitmap(words, _) => {
+                words[x as usize / WORD_SIZE] & bit_mask(x as usize) != 0
+            },
+            Container::Run(runs) => Container::find_run(runs, x).is_some_and(|j| x <= runs[j].1),
+        }
+    }
+    /// Whether an edit took the container past the size where another kind
+    /// is smaller: an array or a run list grown past its limit, or a bitmap
+    /// shrunk to the size of an array.
+    fn needs_repack(&self) -> bool {
+        match self {
+            Container::Array(values) => values.len() > MAX_ARRAY_LEN,
+            Container::Bitmap(_, len) => (*len as usize) <= MAX_ARRAY_LEN,
+            Container::Run(runs) => runs.len() > MAX_RUNS,
+        }
+    }
+    /// Adds `x`, returning whether it was missing.
+    fn insert(&mut self, x: u16) -> bool {
+        let inserted = match self {
+            Container::Array(values) => match values.binary_search(&x) {
+                Ok(_) => false,
+                Err(k) => {
+                    values.insert(k, x);
+                    true
+                },
+            },
+            Container::Bitmap(words, len) => {
+                let (w, mask) = (x as usize / WORD_SIZE, bit_mask(x as usize));
+                let missing = words[w] & mask == 0;
+                words[w] |= mask;
+                *len += missing as u32;
+                missing
+            },
+            Container::Run(runs) => {
+                let j = Container::find_run(runs, x);
+                if j.is_some_and(|j| x <= runs[j].1) {
+                    return false;
+                }
+                let extends_prev = j.filter(|j| runs[*j].1 as u32 + 1 == x as u32);
+                let next = j.map_or(0, |j| j + 1);
+                let extends_next = next < runs.len() && runs[next].0 as u32 == x as u32 + 1;
+                match (extends_prev, extends_next) {
+                    (Some(j), true) => {
+                        runs[j].1 = runs[next].1;
+                        runs.remove(next);
+                    },
+                    (Some(j), false) => runs[j].1 = x,
+                    (None, true) => runs[next].0 = x,
+                    (None, false) => runs.insert(next, (x, x)),
+                }
+                true
+            },
+        };
+        if self.needs_repack() {
+            *self = Container::new_with_words(self.words()).unwrap();
+        }
+        inserted
+    }
+    /// Removes `x`, returning whether it was there.
+    fn remove(&mut self, x: u16) -> bool {
+        let removed = match self {
+            Container::Array(values) => match values.binary_search(&x) {
+                Ok(k) => {
+                    values.remove(k);
+                    true
+                },
+                Err(_) => false,
+            },
+            Container::Bitmap(words, len) => {
+                let (w, mask) = (x as usize / WORD_SIZE, bit_mask(x as usize));
+                let present = words[w] & mask != 0;
+                words[w] &= !mask;
+                *len -= present as u32;
+                present
+            },
+            Container::Run(runs) => match Container::find_run(runs, x) {
+                Some(j) if x <= runs[j].1 => {
+                    let (start, end) = runs[j];
+                    match (start == x, end == x) {
+                        (true, true) => {
+                            runs.remove(j);
+                        },
+                        (true, false) => runs[j].0 = x + 1,
+                        (false, true) => runs[j].1 = x - 1,
+                        (false, false) => {
+                            runs[j].1 = x - 1;
+                            runs.insert(j + 1, (x + 1, end));
+                        },
+                    }
+                    true
+                },
+                _ => false,
+            },
+        };
+        if self.needs_repack() && !self.is_empty() {
+            *self = Container::new_with_words(self.words()).unwrap();
+        }
+        removed
+    }
+    /// Number of values up to `x` included.
+    fn rank(&self, x: u16) -> u32 {
+        match self {
+            Container::Array(values) => values.partition_point(|v| *v <= x) as u32,
+            Container::Bitmap(words, _) => {
+                let w = x as usize / WORD_SIZE;
+                let below: u32 = words[..w].iter().map(|w| w.count_ones()).sum();
+                below + (words[w] & prefix_mask(x as usize % WORD_SIZE + 1)).count_ones()
+            },
+            Container::Run(runs) => {
+                let mut res = 0;
+                for (s, e) in runs {
+                    if *s > x {
+                        break;
+                    }
+                    res += ((*e).min(x) - s) as u32 + 1;
+                }
+                res
+            },
+        }
+    }
+    /// The `k`-th value, counting from zero.
+    fn select(&self, k: u32) -> u16 {
+        match self {
+            Container::Array(values) => values[k as usize],
+            Container::Bitmap(words, _) => {
+                let mut k = k;
+                for (i, w) in words.iter().enumerate() {
+                    let c = w.count_ones();
+                    if k < c {
+                        return (i * WORD_SIZE + select_in_word(*w, k)) as u16;
+                    }
+                    k -= c;
+                }
+                unreachable!()
+            },
+            Container::Run(runs) => {
+                let mut k = k;
+                for (s, e) in runs {
+                    let len = (e - s) as u32 + 1;
+                    if k < len {
+                        return s + k as u16;
+                    }
+                    k -= len;
+                }
+                unreachable!()
+            },
+        }
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = u16> + '_> {
+        match self {
+            Container::Array(values) => Box::new(values.iter().copied()),
+            Container::Bitmap(words, _) => Box::new(Container::ones(words)),
+            Container::Run(runs) => Box::new(runs.iter().flat_map(|(s, e)| *s..=*e)),
+        }
+    }
+    fn union(&self, other: &Container) -> Container {
+        match (self, other) {
+            (Container::Array(a), Container::Array(b)) if a.len() + b.len() <= MAX_ARRAY_LEN => {
+                let mut values = Vec::with_capacity(a.len() + b.len());
+                let (mut i, mut j) = (0, 0);
+                while i < a.len() || j < b.len() {
+                    if j == b.len() || (i < a.len() && a[i] < b[j]) {
+                        values.push(a[i]);
+                        i += 1;
+                    } else {
+                        if i < a.len() && a[i] == b[j] {
+                            i += 1;
+                        }
+                        values.push(b[j]);
+                        j += 1;
+                    }
+                }
+                Container::Array(values)
+            },
+            _ => {
+                let mut words = self.words();
+                for (w, o) in words.iter_mut().zip(other.words()) {
+                    *w |= o;
+                }
+                Container::new_with_words(words).unwrap()
+            },
+        }
+    }
+    fn intersection(&self, other: &Container) -> Option<Container> {
+        match (self, other) {
+            (Container::Array(a), _) => Container::filter(a, |x| other.contains(x)),
+            (_, Container::Array(b)) => Container::filter(b, |x| self.contains(x)),
+            _ => {
+                let mut words = self.words();
+                for (w, o) in words.iter_mut().zip(other.words()) {
+                    *w &= o;
+                }
+                Container::new_with_words(words)
+            },
+        }
+    }
+    fn difference(&self, other: &Container) -> Option<Container> {
+        match self {
+            Container::Array(a) => Container::filter(a, |x| !other.contains(x)),
+            _ => {
+                let mut words = self.words();
+                for (w, o) in words.iter_mut().zip(other.words()) {
+                    *w &= !o;
+                }
+                Container::new_with_words(words)
+            },
+        }
+    }
+    fn filter(values: &[u16], keep: impl Fn(u16) -> bool) -> Option<Container> {
+        let values: Vec<u16> = values.iter().copied().filter(|x| keep(*x)).collect();
+        if values.is_empty() {
+            None
+        } else {
+            Some(Container::Array(values))
+        }
+    }
+    fn size(&self) -> usize {
+        match self {
+            Container::Array(values) => std::mem::size_of_val(&**values),
+            Container::Bitmap(words, _) => std::mem::size_of_val(&**words),
+            Container::Run(runs) => std::mem::size_of_val(&**runs),
+        }
+    }
+}
+
+/// Set of integers cut into chunks of `2^16` consecutive values, each
+/// non-empty chunk kept in whichever of a sorted array, a bitmap or a list of
+/// runs is smallest for it, as in Roaring bitmaps.
+///
+/// The number of values before every chunk is kept alongside, so ranks and
+/// selects binary-search the chunks instead of counting them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HybridBitmap {
+    chunks: Vec<(u64, Container)>,
+    /// `ranks[k]` values in the chunks before `k`, and the total at the end.
+    ranks: Vec<u64>,
+}
+
+impl Default for HybridBitmap {
+    fn default() -> Self {
+        HybridBitmap::new()
+    }
+}
+
+impl HybridBitmap {
+    fn new_with_chunks(chunks: Vec<(u64, Container)>) -> HybridBitmap {
+        let mut ranks = Vec::with_capacity(chunks.len() + 1);
+        ranks.push(0);
+        for (_, c) in &chunks {
+            ranks.push(ranks.last().unwrap() + c.len() as u64);
+        }
+        HybridBitmap { chunks, ranks }
+    }
+    /// Shifts the counts past chunk `k` after it gained or lost a value.
+    fn update_ranks(&mut self, k: usize, inserted: bool) {
+        for r in &mut self.ranks[k + 1..] {
+            if inserted {
+                *r += 1;
+            } else {
+                *r -= 1;
+            }
+        }
+    }
+    fn chunk_len(&self, k: usize) -> u64 {
+        self.ranks[k + 1] - self.ranks[k]
+    }
+    fn split(x: u64) -> (u64, u16) {
+        (x >> CHUNK_BITS, x as u16)
+    }
+    fn find(&self, key: u64) -> Result<usize, usize> {
+        self.chunks.binary_search_by_key(&key, |(k, _)| *k)
+    }
+    /// Merges the chunks of both sets with `op`, which sees `None` for a chunk
+    /// missing from one side.
+    fn merge<F>(&self, other: &HybridBitmap, op: F) -> HybridBitmap
+    where
+        F: Fn(Option<&Container>, Option<&Container>) -> Option<Container>,
+    {
+        let (a, b) = (&self.chunks, &other.chunks);
+        let mut chunks = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            let key = match (a.get(i), b.get(j)) {
+                (Some((ka, _)), Some((kb, _))) => *ka.min(kb),
+                (Some((ka, _)), None) => *ka,
+                (None, Some((kb, _))) => *kb,
+                (None, None) => unreachable!(),
+            };
+            let left = a.get(i).filter(|(k, _)| *k == key).map(|(_, c)| c);
+            let right = b.get(j).filter(|(k, _)| *k == key).map(|(_, c)| c);
+            i += left.is_some() as usize;
+            j += right.is_some() as usize;
+            if let Some(c) = op(left, right) {
+                chunks.push((key, c));
+            }
+        }
+        HybridBitmap::new_with_chunks(chunks)
+    }
+}
+
+impl HybridBitmap /* Public API */ {
+    pub fn new() -> HybridBitmap {
+        HybridBitmap::new_with_chunks(vec![])
+    }
+    /// The set of positions set in `bv`.
+    pub fn new_with_bit_vec(bv: &BitVec) -> HybridBitmap {
+        let chunks = bv
+            .words()
+            .chunks(CHUNK_WORDS)
+            .enumerate()
+            .filter_map(|(key, words)| {
+                let mut words = words.to_vec();
+                words.resize(CHUNK_WORDS, 0);
+                Container::new_with_words(words).map(|c| (key as u64, c))
+            })
+            .collect();
+        HybridBitmap::new_with_chunks(chunks)
+    }
+    /// Bit vector of `size` bits with the values of the set, which must all be
+    /// smaller than `size`, set.
+    pub fn to_bit_vec(&self, size: usize) -> BitVec {
+        assert!(
+            self.max().is_none_or(|max| max < size as u64),
+            "values must be smaller than the bit vector size"
+        );
+        let mut bv = BitVec::new(size);
+        for (key, c) in &self.chunks {
+            let first = *key as usize * CHUNK_WORDS;
+            for (i, w) in c.words().iter().enumerate().filter(|(_, w)| **w != 0) {
+                bv.bv[first + i] = *w;
+            }
+        }
+        bv
+    }
+    /// Number of values.
+    pub fn len(&self) -> u64 {
+        *self.ranks.last().unwrap()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+    pub fn contains(&self, x: u64) -> bool {
+        let (key, low) = HybridBitmap::split(x);
+        self.find(key).is_ok_and(|k| self.chunks[k].1.contains(low))
+    }
+    /// Adds `x`, returning whether it was missing.
+    pub fn insert(&mut self, x: u64) -> bool {
+        let (key, low) = HybridBitmap::split(x);
+        let k = match self.find(key) {
+            Ok(k) => k,
+            Err(k) => {
+                self.chunks.insert(k, (key, Container::Array(vec![])));
+                self.ranks.insert(k + 1, self.ranks[k]);
+                k
+            },
+        };
+        let inserted = self.chunks[k].1.insert(low);
+        if inserted {
+            self.update_ranks(k, true);
+        }
+        inserted
+    }
+    /// Removes `x`, returning whether it was there.
+    pub fn remove(&mut self, x: u64) -> bool {
+        let (key, low) = HybridBitmap::split(x);
+        let Ok(k) = self.find(key) else {
+            return false;
+        };
+        let removed = self.chunks[k].1.remove(low);
+        if removed {
+            self.update_ranks(k, false);
+        }
+        if self.chunks[k].1.is_empty() {
+            self.chunks.remove(k);
+            self.ranks.remove(k + 1);
+        }
+        removed
+    }
+    /// Number of values in `[0, x]`.
+    pub fn rank(&self, x: u64) -> u64 {
+        let (key, low) = HybridBitmap::split(x);
+        match self.find(key) {
+            Ok(k) => self.ranks[k] + self.chunks[k].1.rank(low) as u64,
+            Err(k) => self.ranks[k],
+        }
+    }
+    /// The `i`-th smallest value, counting from one.
+    pub fn select(&self, i: u64) -> Option<u64> {
+        if i == 0 || i > self.len() {
+            return None;
+        }
+        // The chunk holding it is the last one with fewer than i values before.
+        let k = self.ranks.partition_point(|r| *r < i) - 1;
+        let (key, c) = &self.chunks[k];
+        Some(key << CHUNK_BITS | c.select((i - 1 - self.ranks[k]) as u32) as u64)
+    }
+    pub fn min(&self) -> Option<u64> {
+        self.select(1)
+    }
+    pub fn max(&self) -> Option<u64> {
+        let (key, c) = self.chunks.last()?;
+        let last = self.chunk_len(self.chunks.len() - 1) - 1;
+        Some(key << CHUNK_BITS | c.select(last as u32) as u64)
+    }
+    /// Values in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        self.chunks
+            .iter()
+            .flat_map(|(key, c)| c.iter().map(move |low| key << CHUNK_BITS | low as u64))
+    }
+    pub fn union(&self, other: &HybridBitmap) -> HybridBitmap {
+        self.merge(other, |a, b| match (a, b) {
+            (Some(a), Some(b)) => Some(a.union(b)),
+            (a, b) => a.or(b).cloned(),
+        })
+    }
+    pub fn intersection(&self, other: &HybridBitmap) -> HybridBitmap {
+        self.merge(other, |a, b| a?.intersection(b?))
+    }
+    /// Values of `self` missing from `other`.
+    pub fn difference(&self, other: &HybridBitmap) -> HybridBitmap {
+        self.merge(other, |a, b| match b {
+            Some(b) => a?.difference(b),
+            None => a.cloned(),
+        })
+    }
+    /// Re-picks the smallest container of every chunk, which insertions and
+    /// removals do not always do.
+    pub fn optimize(&mut self) {
+        for (_, c) in self.chunks.iter_mut() {
+            *c = Container::new_with_words(c.words()).unwrap();
+        }
+    }
+    pub fn overhead(&self) -> usize {
+        std::mem::size_of_val(&*self.chunks)
+            + std::mem::size_of_val(&*self.ranks)
+            + self.chunks.iter().map(|(_, c)| c.size()).sum::<usize>()
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<HybridBitmap> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: HybridBitmap = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod hybrid_bitmap_tests {
+    use crate::bit_vec::BitVec;
+    use crate::hybrid_bitmap::{Container, HybridBitmap};
+    use crate::rank_support::RankSupport;
+    use rand::Rng;
+    use std::collections::BTreeSet;
+
+    /// A sparse chunk, a dense chunk, a chunk of runs and a few far values.
+    fn mixed_values() -> BTreeSet<u64> {
+        let mut rng = rand::thread_rng();
+        let mut set = BTreeSet::new();
+        for _ in 0..300 {
+            set.insert(rng.gen_range(0..1 << 16));
+        }
+        for _ in 0..40_000 {
+            set.insert(rng.gen_range(1 << 16..2 << 16));
+        }
+        for start in (2 << 16..3 << 16).step_by(5000) {
+            set.extend(start..start + rng.gen_range(1..3000));
+        }
+        for _ in 0..20 {
+            set.insert(rng.gen_range(1 << 40..1 << 41));
+        }
+        set
+    }
+    fn from_set(set: &BTreeSet<u64>) -> HybridBitmap {
+        let mut bitmap = HybridBitmap::new();
+        for x in set {
+            bitmap.insert(*x);
+        }
+        bitmap
+    }
+
+    #[test]
+    fn membership_tests() {
+        let set = mixed_values();
+        let mut bitmap = from_set(&set);
+        bitmap.optimize();
+        let kinds: Vec<&str> = bitmap
+            .chunks
+            .iter()
+            .take(3)
+            .map(|(_, c)| match c {
+                Container::Array(_) => "array",
+                Container::Bitmap(..) => "bitmap",
+                Container::Run(_) => "run",
+            })
+            .collect();
+        assert_eq!(kinds, ["array", "bitmap", "run"]);
+        assert_eq!(bitmap.len(), set.len() as u64);
+        assert!(bitmap.iter().eq(set.iter().copied()));
+        let mut rng = rand::thread_rng();
+        for _ in 0..5000 {
+            let x = rng.gen_range(0..3 << 16);
+            assert_eq!(bitmap.contains(x), set.contains(&x));
+        }
+        assert_eq!(bitmap.min(), set.first().copied());
+        assert_eq!(bitmap.max(), set.last().copied());
+    }
+    #[test]
+    fn insert_remove_tests() {
+        let mut rng = rand::thread_rng();
+        let mut set = BTreeSet::new();
+        let mut bitmap = HybridBitmap::new();
+        // Narrow ranges push containers through every representation.
+        for (round, range) in [(0, 1000), (0, 70_000), (100, 200), (0, 1 << 17)]
+            .iter()
+            .enumerate()
+        {
+            for _ in 0..20_000 {
+                let x = rng.gen_range(range.0..range.1);
+                if round % 2 == 0 || rng.gen_bool(0.3) {
+                    assert_eq!(bitmap.insert(x), set.insert(x));
+                } else {
+                    assert_eq!(bitmap.remove(x), set.remove(&x));
+                }
+            }
+            assert!(bitmap.iter().eq(set.iter().copied()));
+            // The counts kept per chunk follow the edits.
+            assert_eq!(bitmap.len(), set.len() as u64);
+            for (k, x) in set.iter().enumerate().step_by(101) {
+                assert_eq!(bitmap.select(k as u64 + 1), Some(*x));
+                assert_eq!(bitmap.rank(*x), k as u64 + 1);
+            }
+        }
+        for x in set.clone() {
+            assert!(bitmap.remove(x));
+        }
+        assert!(bitmap.is_empty());
+        assert!(!bitmap.remove(5));
+    }
+    #[test]
+    fn run_edit_tests() {
+        let mut bitmap = HybridBitmap::new_with_bit_vec(&{
+            let mut bv = BitVec::new(1 << 16);
+            for i in (100..20_000).chain(30_000..60_000) {
+                bv.set(i);
+            }
+            bv
+        });
+        assert!(matches!(bitmap.chunks[0].1, Container::Run(_)));
+        assert!(bitmap.remove(500));
+        assert!(bitmap.remove(100));
+        assert!(bitmap.remove(19_999));
+        assert!(bitmap.insert(20_000));
+        assert!(bitmap.insert(29_999));
+        assert!(!bitmap.insert(101));
+        for x in 20_001..29_999 {
+            bitmap.insert(x);
+        }
+        let expected: Vec<u64> = (101..500)
+            .chain(501..19_999)
+            .chain(20_000..60_000)
+            .collect();
+        assert!(bitmap.iter().eq(expected.iter().copied()));
+        assert_eq!(
+            bitmap.chunks[0].1,
+            Container::Run(vec![(101, 499), (501, 19_998), (20_000, 59_999)])
+        );
+    }
+    #[test]
+    fn rank_select_tests() {
+        let set = mixed_values();
+        let bitmap = from_set(&set);
+        let values: Vec<u64> = set.iter().copied().collect();
+        for (k, x) in values.iter().enumerate().step_by(37) {
+            assert_eq!(bitmap.select(k as u64 + 1), Some(*x));
+            assert_eq!(bitmap.rank(*x), k as u64 + 1);
+            if *x > 0 {
+                assert_eq!(bitmap.rank(*x - 1), k as u64);
+            }
+        }
+        assert_eq!(bitmap.select(0), None);
+        assert_eq!(bitmap.select(values.len() as u64 + 1), None);
+        assert_eq!(bitmap.rank(u64::MAX), values.len() as u64);
+    }
+    #[test]
+    fn set_algebra_tests() {
+        let mut rng = rand::thread_rng();
+        let a = mixed_values();
+        let mut b = mixed_values();
+        b.extend((0..1000).map(|_| rng.gen_range(0..4 << 16)));
+        let (x, y) = (from_set(&a), from_set(&b));
+        assert!(x.union(&y).iter().eq(a.union(&b).copied()));
+        assert!(x.intersection(&y).iter().eq(a.intersection(&b).copied()));
+        assert!(x.difference(&y).iter().eq(a.difference(&b).copied()));
+        assert!(y.difference(&x).iter().eq(b.difference(&a).copied()));
+        assert!(x.difference(&x).is_empty());
+        assert_eq!(x.union(&HybridBitmap::new()), x);
+        assert_eq!(x.union(&y).len(), a.union(&b).count() as u64);
+    }
+    #[test]
+    fn bit_vec_conversion_tests() {
+        for size in [0, 100, 1 << 16, 200_000] {
+            let bv = BitVec::new_with_random(size);
+            let bitmap = HybridBitmap::new_with_bit_vec(&bv);
+            let r = RankSupport::new(&bv);
+            assert_eq!(bitmap.len(), bv.count_ones());
+            for i in (0..size).step_by(97) {
+                assert_eq!(bitmap.contains(i as u64), bv.get(i));
+                assert_eq!(bitmap.rank(i as u64), r.rank1(i as u64));
+            }
+            assert_eq!(bitmap.to_bit_vec(size), bv);
+        }
+        // Random bits are dense enough for bitmaps, which keep the words as is.
+        let bv = BitVec::new_with_random(1 << 16);
+        let bitmap = HybridBitmap::new_with_bit_vec(&bv);
+        match &bitmap.chunks[0].1 {
+            Container::Bitmap(words, _) => assert_eq!(&words[..], bv.words()),
+            _ => panic!("expected a bitmap container"),
+        }
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let bitmap = from_set(&mixed_values());
+        bitmap.save("example_hybrid_bitmap.txt")?;
+        let bitmap2 = HybridBitmap::load("example_hybrid_bitmap.txt".to_owned())?;
+        assert_eq!(bitmap, bitmap2);
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+use crate::bit_vec::{BitVec, WORD_SIZE};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// Number of bits needed to write `value`, `0` for `0`.
+pub(in crate) fn bit_width(value: u64) -> usize {
+    (u64::BITS - value.leading_zeros()) as usize
+}
+
+/// Vector of unsigned integers of `width <= 64` bits each, packed back to back
+/// in a [`BitVec`], most significant bit first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntVec {
+    bits: BitVec,
+    width: usize,
+    len: usize,
+}
+
+/// Iterator over the values of an [`IntVec`].
+pub struct IntVecIter<'a> {
+    v: &'a IntVec,
+    i: usize,
+}
+
+impl Iterator for IntVecIter<'_> {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        let res = self.v.get(self.i)?;
+        self.i += 1;
+        Some(res)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.v.len - self.i;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for IntVecIter<'_> {}
+
+impl<'a> IntoIterator for &'a IntVec {
+    type Item = u64;
+    type IntoIter = IntVecIter<'a>;
+    fn into_iter(self) -> IntVecIter<'a> {
+        self.iter()
+    }
+}
+
+impl IntVec {
+    fn check_fits(&self, value: u64) {
+        assert!(
+            bit_width(value) <= self.width,
+            "value {} does not fit in {} bits",
+            value,
+            self.width
+        );
+    }
+}
+
+impl IntVec /* Public API */ {
+    /// Empty vector of `width`-bit integers.
+    pub fn new(width: usize) -> IntVec {
+        IntVec::new_with_len(0, width)
+    }
+    /// `len` zeros of `width` bits.
+    pub fn new_with_len(len: usize, width: usize) -> IntVec {
+        assert!(width <= WORD_SIZE, "IntVec width must be at most 64");
+        IntVec {
+            bits: BitVec::new_with_zeros(len * width),
+            width,
+            len,
+        }
+    }
+    /// Packs `values` with the width of the largest one.
+    pub fn new_with_values(values: &[u64]) -> IntVec {
+        let width = values.iter().map(|v| bit_width(*v)).max().unwrap_or(0);
+        IntVec::new_with_values_and_width(values, width)
+    }
+    pub fn new_with_values_and_width(values: &[u64], width: usize) -> IntVec {
+        let mut res = IntVec::new_with_len(values.len(), width);
+        for (i, v) in values.iter().enumerate() {
+            res.set(i, *v);
+        }
+        res
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Bits per value.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn get(&self, i: usize) -> Option<u64> {
+        if i >= self.len {
+            return None;
+        }
+        Some(self.bits.get_bits(i * self.width, self.width))
+    }
+    /// Overwrites the value at `i`, which must fit in `width` bits.
+    pub fn set(&mut self, i: usize, value: u64) {
+        assert!(
+            i < self.len,
+            "index {} out of range for length {}",
+            i,
+            self.len
+        );
+        self.check_fits(value);
+        self.bits.set_bits(i * self.width, self.width, value);
+    }
+    /// Appends `value`, which must fit in `width` bits.
+    pub fn push(&mut self, value: u64) {
+        self.check_fits(value);
+        self.bits.resize((self.len + 1) * self.width, false);
+        self.len += 1;
+        self.bits
+            .set_bits((self.len - 1) * self.width, self.width, value);
+    }
+    pub fn iter(&self) -> IntVecIter<'_> {
+        IntVecIter { v: self, i: 0 }
+    }
+    pub fn overhead(&self) -> usize {
+        std::mem::size_of_val(self.bits.words())
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<IntVec> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: IntVec = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod int_vec_tests {
+    use crate::int_vec::IntVec;
+    use rand::Rng;
+
+    #[test]
+    fn get_set_tests() {
+        let mut rng = rand::thread_rng();
+        for width in [0, 1, 3, 7, 13, 32, 63, 64] {
+            let max = if width == 64 {
+                u64::MAX
+            } else {
+                (1 << width) - 1
+            };
+            let values: Vec<u64> = (0..500).map(|_| rng.gen_range(0..=max)).collect();
+            let mut v = IntVec::new_with_values_and_width(&values, width);
+            assert_eq!(v.len(), 500);
+            for (i, x) in values.iter().enumerate() {
+                assert_eq!(v.get(i), Some(*x));
+            }
+            assert_eq!(v.get(500), None);
+            v.set(17, max);
+            v.set(18, 0);
+            assert_eq!(v.get(16), Some(values[16]));
+            assert_eq!((v.get(17), v.get(18)), (Some(max), Some(0)));
+            assert_eq!(v.get(19), Some(values[19]));
+        }
+    }
+    #[test]
+    fn push_iter_tests() {
+        let mut rng = rand::thread_rng();
+        let values: Vec<u64> = (0..1000).map(|_| rng.gen_range(0..1 << 11)).collect();
+        let mut v = IntVec::new(11);
+        for x in &values {
+            v.push(*x);
+        }
+        assert_eq!(v.iter().collect::<Vec<u64>>(), values);
+        assert_eq!(v.iter().len(), 1000);
+        assert_eq!((&v).into_iter().sum::<u64>(), values.iter().sum::<u64>());
+        assert_eq!(v, IntVec::new_with_values_and_width(&values, 11));
+    }
+    #[test]
+    fn width_detection_tests() {
+        assert_eq!(IntVec::new_with_values(&[]).width(), 0);
+        assert_eq!(IntVec::new_with_values(&[0, 0]).width(), 0);
+        assert_eq!(IntVec::new_with_values(&[1, 0]).width(), 1);
+        assert_eq!(IntVec::new_with_values(&[5, 255, 3]).width(), 8);
+        assert_eq!(IntVec::new_with_values(&[256]).width(), 9);
+        assert_eq!(IntVec::new_with_values(&[u64::MAX]).width(), 64);
+        let v = IntVec::new_with_values(&[3; 1000]);
+        assert_eq!(v.overhead(), 2000_usize.div_ceil(64) * 8);
+    }
+    #[test]
+    #[should_panic]
+    fn overflow_test() {
+        let mut v = IntVec::new(4);
+        v.push(16);
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let v = IntVec::new_with_values(&[9, 8, 7, 1000, 0]);
+        v.save("example_int_vec.txt")?;
+        let v2 = IntVec::load("example_int_vec.txt".to_owned())?;
+        assert_eq!(v, v2);
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+use crate::bit_vec::BitVec;
+use crate::rank_support::RankSupport;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{Read, Write};
+
+const DEFAULT_K: usize = 2;
+
+/// k²-tree over an `n x n` binary matrix, such as the adjacency matrix of a
+/// graph.
+///
+/// The matrix, padded to a side of `k^height`, is split into `k²` submatrices
+/// in row-major order, one bit per submatrix telling whether it holds a one;
+/// non-empty submatrices are split again down to single cells. Bits of the
+/// internal levels are concatenated level by level in `t`, those of the cells
+/// in `l`. The children of the set bit at position `z` start at
+/// `rank1(t, z) * k²` in `t` followed by `l`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct K2Tree {
+    t: RankSupport<'static>,
+    l: BitVec,
+    k: usize,
+    height: usize,
+    size: usize,
+    len: usize,
+}
+
+/// Half-open rectangle `[rows.0, rows.1) x [cols.0, cols.1)`.
+type Rect = ((usize, usize), (usize, usize));
+
+impl K2Tree {
+    fn bit(&self, z: usize) -> bool {
+        let internal = self.t.bv.len();
+        if z < internal {
+            self.t.bv.get(z)
+        } else {
+            self.l.get(z - internal)
+        }
+    }
+    fn children(&self, z: usize) -> usize {
+        self.t.rank1_exclusive(z + 1) as usize * self.k * self.k
+    }
+    /// Side of the submatrices below the root.
+    fn root_side(&self) -> usize {
+        self.k.pow(self.height as u32 - 1)
+    }
+    /// Appends the cells of `rect` set in the submatrices starting at
+    /// `start`, each of the given side, whose parent has its corner at
+    /// `(row, col)`.
+    fn collect(
+        &self,
+        start: usize,
+        side: usize,
+        row: usize,
+        col: usize,
+        rect: Rect,
+        res: &mut Vec<(usize, usize)>,
+    ) {
+        let ((r1, r2), (c1, c2)) = rect;
+        for i in 0..self.k {
+            let r = row + i * side;
+            if r >= r2 || r + side <= r1 {
+                continue;
+            }
+            for j in 0..self.k {
+                let c = col + j * side;
+                if c >= c2 || c + side <= c1 {
+                    continue;
+                }
+                let z = start + i * self.k + j;
+                if !self.bit(z) {
+                    continue;
+                }
+                if side == 1 {
+                    res.push((r, c));
+                } else {
+                    self.collect(self.children(z), side / self.k, r, c, rect, res);
+                }
+            }
+        }
+    }
+}
+
+impl K2Tree /* Public API */ {
+    /// k²-tree with `k = 2` of the `size x size` matrix whose ones are the
+    /// `(row, column)` pairs of `edges`.
+    pub fn new(size: usize, edges: &[(usize, usize)]) -> K2Tree {
+        K2Tree::new_with_k(size, DEFAULT_K, edges)
+    }
+    pub fn new_with_k(size: usize, k: usize, edges: &[(usize, usize)]) -> K2Tree {
+        assert!(k >= 2, "k2-tree arity must be at least 2");
+        let mut edges = edges.to_vec();
+        edges.sort_unstable();
+        edges.dedup();
+        assert!(
+            edges.iter().all(|(u, v)| *u < size && *v < size),
+            "edges must lie inside the matrix"
+        );
+        let mut height = 1;
+        while k.pow(height as u32) < size {
+            height += 1;
+        }
+
+        // Level by level, each node being the edges of its submatrix relative
+        // to the submatrix corner.
+        let mut t = vec![];
+        let mut l = vec![];
+        let len = edges.len();
+        let mut level = if edges.is_empty() {
+            vec![]
+        } else {
+            vec![edges]
+        };
+        let mut side = k.pow(height as u32 - 1);
+        while !level.is_empty() {
+            let mut next = vec![];
+            for node in level {
+                let mut buckets = vec![vec![]; k * k];
+                for (u, v) in node {
+                    buckets[(u / side) * k + v / side].push((u % side, v % side));
+                }
+                for bucket in buckets {
+                    let bits = if side == 1 { &mut l } else { &mut t };
+                    bits.push(!bucket.is_empty());
+                    if side > 1 && !bucket.is_empty() {
+                        next.push(bucket);
+                    }
+                }
+            }
+            level = next;
+            side /= k;
+        }
+
+        let to_bit_vec = |bits: &[bool]| {
+            let mut bv = BitVec::new(bits.len());
+            for (i, bit) in bits.iter().enumerate() {
+                if *bit {
+                    bv.set(i);
+                }
+            }
+            bv
+        };
+        K2Tree {
+            t: RankSupport::new_with_index_computation(Cow::Owned(to_bit_vec(&t))),
+            l: to_bit_vec(&l),
+            k,
+            height,
+            size,
+            len,
+        }
+    }
+    /// Side of the matrix.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+    /// Number of ones.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn has_edge(&self, u: usize, v: usize) -> bool {
+        if u >= self.size || v >= self.size || self.is_empty() {
+            return false;
+        }
+        let mut side = self.root_side();
+        let mut start = 0;
+        let (mut u, mut v) = (u, v);
+        loop {
+            let z = start + (u / side) * self.k + v / side;
+            if !self.bit(z) {
+                return false;
+            }
+            if side == 1 {
+                return true;
+            }
+            start = self.children(z);
+            u %= side;
+            v %= side;
+            side /= self.k;
+        }
+    }
+    /// Columns of the ones in row `u`, in increasing order.
+    pub fn successors(&self, u: usize) -> Vec<usize> {
+        self.range(u, u + 1, 0, self.size)
+            .into_iter()
+            .map(|(_, v)| v)
+            .collect()
+    }
+    /// Rows of the ones in column `v`, in increasing order.
+    pub fn predecessors(&self, v: usize) -> Vec<usize> {
+        self.range(0, self.size, v, v + 1)
+            .into_iter()
+            .map(|(u, _)| u)
+            .collect()
+    }
+    /// Ones in rows `[r1, r2)` and columns `[c1, c2)`, sorted by row then
+    /// column.
+    pub fn range(&self, r1: usize, r2: usize, c1: usize, c2: usize) -> Vec<(usize, usize)> {
+        let mut res = vec![];
+        if self.is_empty() || r1 >= r2 || c1 >= c2 {
+            return res;
+        }
+        self.collect(0, self.root_side(), 0, 0, ((r1, r2), (c1, c2)), &mut res);
+        res.sort_unstable();
+        res
+    }
+    pub fn overhead(&self) -> usize {
+        std::mem::size_of_val(self.t.bv.words())
+            + self.t.overhead()
+            + std::mem::size_of_val(self.l.words())
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<K2Tree> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: K2Tree = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod k2_tree_tests {
+    use crate::k2_tree::K2Tree;
+    use rand::Rng;
+    use std::collections::BTreeSet;
+
+    fn random_edges(n: usize, m: usize) -> Vec<(usize, usize)> {
+        let mut rng = rand::thread_rng();
+        (0..m)
+            .map(|_| (rng.gen_range(0..n), rng.gen_range(0..n)))
+            .collect()
+    }
+
+    #[test]
+    fn has_edge_tests() {
+        for (n, m, k) in [
+            (1, 1, 2),
+            (5, 10, 2),
+            (100, 300, 2),
+            (100, 300, 3),
+            (1000, 5000, 4),
+        ] {
+            let edges = random_edges(n, m);
+            let tree = K2Tree::new_with_k(n, k, &edges);
+            let set: BTreeSet<(usize, usize)> = edges.iter().copied().collect();
+            assert_eq!(tree.len(), set.len());
+            for (u, v) in &edges {
+                assert!(tree.has_edge(*u, *v));
+            }
+            for (u, v) in random_edges(n, 1000) {
+                assert_eq!(tree.has_edge(u, v), set.contains(&(u, v)));
+            }
+            assert!(!tree.has_edge(n, 0));
+        }
+    }
+    #[test]
+    fn neighbour_tests() {
+        let n = 300;
+        let edges = random_edges(n, 2000);
+        let tree = K2Tree::new(n, &edges);
+        let set: BTreeSet<(usize, usize)> = edges.iter().copied().collect();
+        for x in 0..n {
+            let succ: Vec<usize> = set.iter().filter(|e| e.0 == x).map(|e| e.1).collect();
+            let pred: Vec<usize> = set.iter().filter(|e| e.1 == x).map(|e| e.0).collect();
+            assert_eq!(tree.successors(x), succ);
+            assert_eq!(tree.predecessors(x), pred);
+        }
+    }
+    #[test]
+    fn range_tests() {
+        let n = 200;
+        let edges = random_edges(n, 3000);
+        for k in [2, 5] {
+            let tree = K2Tree::new_with_k(n, k, &edges);
+            let set: BTreeSet<(usize, usize)> = edges.iter().copied().collect();
+            for (r1, r2, c1, c2) in [
+                (0, 200, 0, 200),
+                (10, 20, 30, 90),
+                (199, 200, 0, 1),
+                (50, 40, 0, 10),
+            ] {
+                let expected: Vec<(usize, usize)> = set
+                    .iter()
+                    .filter(|(u, v)| r1 <= *u && *u < r2 && c1 <= *v && *v < c2)
+                    .copied()
+                    .collect();
+                assert_eq!(tree.range(r1, r2, c1, c2), expected);
+            }
+        }
+    }
+    #[test]
+    fn empty_tests() {
+        let tree = K2Tree::new(10, &[]);
+        assert!(tree.is_empty());
+        assert!(!tree.has_edge(3, 3));
+        assert_eq!(tree.successors(3), Vec::<usize>::new());
+        let tree = K2Tree::new(0, &[]);
+        assert_eq!(tree.range(0, 10, 0, 10), vec![]);
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let edges = random_edges(64, 200);
+        let tree = K2Tree::new(64, &edges);
+        tree.save("example_k2_tree.txt")?;
+        let tree2 = K2Tree::load("example_k2_tree.txt".to_owned())?;
+        for (u, v) in &edges {
+            assert!(tree2.has_edge(*u, *v));
+        }
+        assert_eq!(tree2.len(), tree.len());
+        Ok(())
+    }
+}
@@ -1,9 +1,40 @@
+mod bit_io;
 mod bit_vec;
+mod bp_tree;
+mod dac;
+mod dynamic_bit_vec;
+mod elias_fano;
+mod fm_index;
+mod hybrid_bitmap;
+mod int_vec;
+mod k2_tree;
+mod louds;
 mod rank_support;
+mod rle_bit_vec;
+mod rmq;
+mod rrr;
 mod select_support;
 mod sparse_array;
+pub mod suffix;
+mod wavelet_matrix;
+mod wavelet_tree;
 
-pub use bit_vec::BitVec;
+pub use bit_io::{BitReader, BitWriter};
+pub use bit_vec::{BitPositions, BitRuns, BitVec, BitVecIter};
+pub use bp_tree::BpTree;
+pub use dac::Dac;
+pub use dynamic_bit_vec::DynamicBitVec;
+pub use elias_fano::EliasFano;
+pub use fm_index::FmIndex;
+pub use hybrid_bitmap::HybridBitmap;
+pub use int_vec::{IntVec, IntVecIter};
+pub use k2_tree::K2Tree;
+pub use louds::{LoudsTree, LoudsTrie};
 pub use rank_support::RankSupport;
+pub use rle_bit_vec::RleBitVec;
+pub use rmq::Rmq;
+pub use rrr::RrrBitVec;
 pub use select_support::SelectSupport;
 pub use sparse_array::SparseArray;
+pub use wavelet_matrix::WaveletMatrix;
+pub use wavelet_tree::WaveletTree;
@@ -0,0 +1,428 @@
+use crate::bit_vec::BitVec;
+use crate::select_support::SelectSupport;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// Ordinal tree in level-order unary degree sequence: after a leading `10`
+/// for a virtual super-root, every node in breadth-first order writes its
+/// degree in unary (`1^d 0`), `2n + 1` bits for `n` nodes.
+///
+/// Nodes are numbered in breadth-first order from the root `0`. Node `x` owns
+/// the `(x + 1)`-th one of the sequence, and its children's ones follow the
+/// `(x + 1)`-th zero, so children of a node get consecutive numbers.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoudsTree {
+    bits: SelectSupport<'static>,
+    len: usize,
+}
+
+impl LoudsTree {
+    /// Position of the first child's one in the degree sequence of `x`.
+    fn children_start(&self, x: usize) -> usize {
+        self.bits.select0(x as u64 + 1).unwrap() as usize + 1
+    }
+    /// Node whose one sits at position `p`.
+    fn node_at(&self, p: usize) -> usize {
+        self.bits.r.rank1_exclusive(p) as usize
+    }
+}
+
+impl LoudsTree /* Public API */ {
+    /// Builds the tree from the degrees of its nodes in breadth-first order.
+    pub fn new_with_degrees<I: IntoIterator<Item = usize>>(degrees: I) -> LoudsTree {
+        let mut bits = vec![true, false];
+        let mut len = 0;
+        // Nodes so far plus their children; every node but the root must be
+        // one of the children.
+        let mut reached = 1;
+        for degree in degrees {
+            assert!(len < reached, "degrees must describe a single tree");
+            bits.extend(std::iter::repeat_n(true, degree));
+            bits.push(false);
+            reached += degree;
+            len += 1;
+        }
+        let mut bv = BitVec::new(bits.len());
+        for (i, bit) in bits.iter().enumerate() {
+            if *bit {
+                bv.set(i);
+            }
+        }
+        assert!(
+            len == 0 || reached == len,
+            "degrees must describe a single tree"
+        );
+        LoudsTree {
+            bits: SelectSupport::new_with_bit_vec(bv),
+            len,
+        }
+    }
+    /// Builds the tree rooted at `root`, `children` listing the children of a
+    /// node in order.
+    pub fn new_with_children<N, I, F>(root: N, children: F) -> LoudsTree
+    where
+        I: IntoIterator<Item = N>,
+        F: Fn(&N) -> I,
+    {
+        let mut degrees = vec![];
+        let mut queue = VecDeque::from([root]);
+        while let Some(x) = queue.pop_front() {
+            let len = queue.len();
+            queue.extend(children(&x));
+            degrees.push(queue.len() - len);
+        }
+        LoudsTree::new_with_degrees(degrees)
+    }
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn root(&self) -> Option<usize> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(0)
+        }
+    }
+    pub fn degree(&self, x: usize) -> usize {
+        if x >= self.len {
+            return 0;
+        }
+        let start = self.children_start(x);
+        self.bits.select0(x as u64 + 2).unwrap() as usize - start
+    }
+    pub fn is_leaf(&self, x: usize) -> bool {
+        self.degree(x) == 0
+    }
+    /// The `k`-th child of `x`, counting from zero.
+    pub fn child(&self, x: usize, k: usize) -> Option<usize> {
+        if k >= self.degree(x) {
+            return None;
+        }
+        Some(self.node_at(self.children_start(x) + k))
+    }
+    pub fn first_child(&self, x: usize) -> Option<usize> {
+        self.child(x, 0)
+    }
+    pub fn next_sibling(&self, x: usize) -> Option<usize> {
+        if x == 0 || x >= self.len {
+            return None;
+        }
+        let p = self.bits.select1(x as u64 + 1).unwrap() as usize;
+        if self.bits.r.bv.get(p + 1) {
+            Some(x + 1)
+        } else {
+            None
+        }
+    }
+    pub fn parent(&self, x: usize) -> Option<usize> {
+        if x == 0 || x >= self.len {
+            return None;
+        }
+        // The zeros before the node's one close the degrees of the super-root
+        // and of every node up to its parent.
+        let p = self.bits.select1(x as u64 + 1).unwrap() as usize;
+        Some(p - x - 1)
+    }
+    pub fn overhead(&self) -> usize {
+        self.bits.overhead()
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<LoudsTree> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: LoudsTree = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+/// Static set of byte strings stored as a [`LoudsTree`] trie.
+///
+/// Each node keeps the byte labelling the edge from its parent, children being
+/// ordered by label. Nodes ending a key are marked in a bit vector whose rank
+/// gives every key an id in `[0, len)`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoudsTrie {
+    tree: LoudsTree,
+    labels: Vec<u8>,
+    terminals: SelectSupport<'static>,
+}
+
+impl LoudsTrie {
+    /// Child of `x` whose edge is labelled `b`.
+    fn child_with_label(&self, x: usize, b: u8) -> Option<usize> {
+        let first = self.tree.first_child(x)?;
+        let end = first + self.tree.degree(x);
+        self.labels[first..end]
+            .binary_search(&b)
+            .ok()
+            .map(|k| first + k)
+    }
+    /// Node reached by reading `key` from the root.
+    fn walk(&self, key: &[u8]) -> Option<usize> {
+        let mut x = self.tree.root()?;
+        for b in key {
+            x = self.child_with_label(x, *b)?;
+        }
+        Some(x)
+    }
+    fn is_terminal(&self, x: usize) -> bool {
+        self.terminals.r.bv.get(x)
+    }
+    /// Appends the keys below `x` in lexicographic order, `prefix` spelling
+    /// the path to `x`.
+    fn collect(&self, x: usize, prefix: &mut Vec<u8>, res: &mut Vec<Vec<u8>>) {
+        if self.is_terminal(x) {
+            res.push(prefix.clone());
+        }
+        let degree = self.tree.degree(x);
+        if let Some(first) = self.tree.first_child(x) {
+            for child in first..first + degree {
+                prefix.push(self.labels[child]);
+                self.collect(child, prefix, res);
+                prefix.pop();
+            }
+        }
+    }
+}
+
+impl LoudsTrie /* Public API */ {
+    pub fn new<K: AsRef<[u8]>>(keys: &[K]) -> LoudsTrie {
+        let mut keys: Vec<&[u8]> = keys.iter().map(|k| k.as_ref()).collect();
+        keys.sort_unstable();
+        keys.dedup();
+        // Breadth-first over nodes given as a range of the sorted keys sharing
+        // the node's path, together with the path length.
+        let mut degrees = vec![];
+        let mut labels = vec![0];
+        let mut terminal = vec![];
+        let mut queue = VecDeque::from([(0, keys.len(), 0)]);
+        while let Some((lo, hi, depth)) = queue.pop_front() {
+            // A key ending here sorts first in the range.
+            let ends_here = lo < hi && keys[lo].len() == depth;
+            terminal.push(ends_here);
+            let mut i = lo + ends_here as usize;
+            let mut degree = 0;
+            while i < hi {
+                let b = keys[i][depth];
+                let start = i;
+                while i < hi && keys[i][depth] == b {
+                    i += 1;
+                }
+                labels.push(b);
+                queue.push_back((start, i, depth + 1));
+                degree += 1;
+            }
+            degrees.push(degree);
+        }
+        let mut terminals = BitVec::new(terminal.len());
+        for (x, t) in terminal.iter().enumerate() {
+            if *t {
+                terminals.set(x);
+            }
+        }
+        LoudsTrie {
+            tree: LoudsTree::new_with_degrees(degrees),
+            labels,
+            terminals: SelectSupport::new_with_bit_vec(terminals),
+        }
+    }
+    /// Number of keys.
+    pub fn len(&self) -> usize {
+        self.terminals.r.count_ones() as usize
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.lookup(key).is_some()
+    }
+    /// Id of `key`, in `[0, len)`.
+    pub fn lookup(&self, key: &[u8]) -> Option<usize> {
+        let x = self.walk(key)?;
+        if self.is_terminal(x) {
+            Some(self.terminals.r.rank1_exclusive(x) as usize)
+        } else {
+            None
+        }
+    }
+    /// The key with the given id.
+    pub fn key(&self, id: usize) -> Option<Vec<u8>> {
+        let mut x = self.terminals.select1(id as u64 + 1)? as usize;
+        let mut key = vec![];
+        while let Some(parent) = self.tree.parent(x) {
+            key.push(self.labels[x]);
+            x = parent;
+        }
+        key.reverse();
+        Some(key)
+    }
+    /// Keys starting with `prefix`, in lexicographic order.
+    pub fn predictive_search(&self, prefix: &[u8]) -> Vec<Vec<u8>> {
+        let mut res = vec![];
+        if let Some(x) = self.walk(prefix) {
+            self.collect(x, &mut prefix.to_vec(), &mut res);
+        }
+        res
+    }
+    /// Length of the longest key that is a prefix of `query`.
+    pub fn longest_prefix(&self, query: &[u8]) -> Option<usize> {
+        let mut x = self.tree.root()?;
+        let mut res = None;
+        for (depth, b) in query.iter().enumerate() {
+            if self.is_terminal(x) {
+                res = Some(depth);
+            }
+            match self.child_with_label(x, *b) {
+                Some(child) => x = child,
+                None => return res,
+            }
+        }
+        if self.is_terminal(x) {
+            res = Some(query.len());
+        }
+        res
+    }
+    pub fn overhead(&self) -> usize {
+        self.tree.overhead()
+            + std::mem::size_of_val(self.tree.bits.r.bv.words())
+            + std::mem::size_of_val(&*self.labels)
+            + std::mem::size_of_val(self.terminals.r.bv.words())
+            + self.terminals.overhead()
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<LoudsTrie> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: LoudsTrie = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod louds_tests {
+    use crate::louds::{LoudsTree, LoudsTrie};
+    use rand::Rng;
+    use std::collections::BTreeSet;
+
+    fn random_keys(n: usize, max_len: usize, alphabet: &[u8]) -> Vec<Vec<u8>> {
+        let mut rng = rand::thread_rng();
+        (0..n)
+            .map(|_| {
+                let len = rng.gen_range(0..=max_len);
+                (0..len)
+                    .map(|_| alphabet[rng.gen_range(0..alphabet.len())])
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tree_tests() {
+        let mut rng = rand::thread_rng();
+        for n in [1, 2, 10, 500, 3000] {
+            // Random tree whose nodes are already numbered in breadth-first
+            // order: parents are non-decreasing.
+            let mut parents = vec![0; n];
+            for x in 1..n {
+                parents[x] = rng.gen_range(parents[x - 1]..x);
+            }
+            let mut children = vec![vec![]; n];
+            for x in 1..n {
+                children[parents[x]].push(x);
+            }
+            let tree = LoudsTree::new_with_children(0, |x| children[*x].clone());
+            assert_eq!(tree.len(), n);
+            assert_eq!(tree.parent(0), None);
+            for x in 0..n {
+                if x > 0 {
+                    assert_eq!(tree.parent(x), Some(parents[x]));
+                }
+                assert_eq!(tree.degree(x), children[x].len());
+                assert_eq!(tree.is_leaf(x), children[x].is_empty());
+                for (k, c) in children[x].iter().enumerate() {
+                    assert_eq!(tree.child(x, k), Some(*c));
+                    assert_eq!(tree.next_sibling(*c), children[x].get(k + 1).copied());
+                }
+                assert_eq!(tree.child(x, children[x].len()), None);
+            }
+        }
+        assert!(LoudsTree::new_with_degrees([]).is_empty());
+    }
+    #[test]
+    fn lookup_tests() {
+        let keys = random_keys(2000, 12, b"ACGT");
+        let trie = LoudsTrie::new(&keys);
+        let set: BTreeSet<Vec<u8>> = keys.iter().cloned().collect();
+        assert_eq!(trie.len(), set.len());
+        let mut ids = BTreeSet::new();
+        for key in &set {
+            let id = trie.lookup(key).unwrap();
+            assert!(id < trie.len());
+            assert_eq!(trie.key(id).as_ref(), Some(key));
+            ids.insert(id);
+        }
+        assert_eq!(ids.len(), set.len());
+        for key in random_keys(500, 14, b"ACGTN") {
+            assert_eq!(trie.contains(&key), set.contains(&key));
+        }
+        assert_eq!(trie.key(trie.len()), None);
+    }
+    #[test]
+    fn predictive_search_tests() {
+        let keys = random_keys(1000, 8, b"abc");
+        let trie = LoudsTrie::new(&keys);
+        let set: BTreeSet<Vec<u8>> = keys.iter().cloned().collect();
+        for prefix in [&b""[..], b"a", b"ab", b"cab", b"abcabc", b"d"] {
+            let expected: Vec<Vec<u8>> = set
+                .iter()
+                .filter(|k| k.starts_with(prefix))
+                .cloned()
+                .collect();
+            assert_eq!(trie.predictive_search(prefix), expected);
+        }
+    }
+    #[test]
+    fn longest_prefix_tests() {
+        let trie = LoudsTrie::new(&["a", "ab", "abcd", "b", "bcd", ""]);
+        assert_eq!(trie.longest_prefix(b"abc"), Some(2));
+        assert_eq!(trie.longest_prefix(b"abcde"), Some(4));
+        assert_eq!(trie.longest_prefix(b"bc"), Some(1));
+        assert_eq!(trie.longest_prefix(b"c"), Some(0));
+        let trie = LoudsTrie::new(&["ACGT", "ACG"]);
+        assert_eq!(trie.longest_prefix(b"AC"), None);
+        assert_eq!(trie.longest_prefix(b"ACGTT"), Some(4));
+        let empty = LoudsTrie::new::<&str>(&[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.longest_prefix(b"A"), None);
+        assert!(!empty.contains(b""));
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let keys = random_keys(100, 6, b"xyz");
+        let trie = LoudsTrie::new(&keys);
+        trie.save("example_louds_trie.txt")?;
+        let trie2 = LoudsTrie::load("example_louds_trie.txt".to_owned())?;
+        for key in &keys {
+            assert!(trie2.contains(key));
+        }
+        Ok(())
+    }
+}
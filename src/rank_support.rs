@@ -2,128 +2,124 @@
 
 use crate::bit_vec::*;
 use serde::{Deserialize, Serialize};
-use serde_json;
 use std::borrow::{Borrow, Cow};
 use std::fs::File;
 use std::io::prelude::*;
 
+/// Bits covered by one superblock of counters.
+pub(in crate) const SUPER_BLOCK_SIZE: usize = 512;
+/// Words covered by one superblock of counters.
+pub(in crate) const WORDS_PER_SUPER_BLOCK: usize = SUPER_BLOCK_SIZE / WORD_SIZE;
+/// Width of a relative (per word) counter inside a superblock.
+const BLOCK_COUNTER_WIDTH: usize = 9;
+
+/// Constant-time rank over a [`BitVec`], laid out after Vigna's rank9.
+///
+/// For every 512-bit superblock `counts` holds two interleaved words: the
+/// number of ones before the superblock, followed by seven 9-bit counters
+/// packed into one word, the `k`-th of which is the number of ones in the
+/// first `k` words of the superblock. A final pair holds the total, so
+/// `rank1` reads one pair of adjacent words plus the bit vector word and
+/// finishes with a hardware popcount.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RankSupport<'bv> {
     pub(crate) bv: Cow<'bv, BitVec>,
-    pub(crate) rs: Vec<BitVec>,
-    pub(crate) rb: Vec<Vec<BitVec>>,
-    pub(crate) rp: Vec<Vec<BitVec>>,
+    pub(crate) counts: Vec<u64>,
 }
 
 impl<'bv> RankSupport<'bv> /* Data Structure Construction */ {
-    fn compute_rs(bv: &BitVec) -> Vec<BitVec> {
-        let log2n = (bv.size as f64).log2();
-        let super_block_size = BitVecSize((log2n * log2n / 2.0).ceil() as usize);
-        // println!("SuperBlockSize: {:?}", super_block_size);
-        let super_block_space = BitVecSize(((bv.size + 1) as f64).log2().ceil() as usize);
-        let vec_size = (bv.size as f64 / (super_block_size.to_usize() as f64)).ceil() as usize;
-        let mut super_blocks: Vec<u64> = Vec::with_capacity(vec_size);
-        for _ in 0..vec_size {
-            super_blocks.push(0);
-        }
-        // println!("Before {:?}", super_blocks);
-        let mut count = 0;
-        for i in 0..(vec_size - 1) {
-            for j in 0..super_block_size.to_usize() {
-                count += bv.get_u8(i * super_block_size.to_usize() + j) as u64;
-            }
-            super_blocks[i + 1] = count;
-            // println!("At {}: {:?}", i, super_blocks);
-        }
-        // println!("SuperBlocks {:?}", super_blocks);
-        super_blocks
-            .iter()
-            .map(|x| BitVec::from_u64(x.clone(), super_block_space.to_usize()))
-            .collect()
-    }
-    fn compute_rb(bv: &BitVec, rs: &Vec<BitVec>) -> Vec<Vec<BitVec>> {
-        let log2n = (bv.size as f64).log2();
-        let super_block_size = BitVecSize((log2n * log2n / 2.0).ceil() as usize);
-        let block_size = BitVecSize((log2n / 2.0).ceil() as usize);
-        // println!("Blocksize: {:?}", block_size);
-        let block_space = BitVecSize(((super_block_size.to_usize()) as f64).log2().ceil() as usize);
-        let vec_size = rs.len();
-        let sub_vec_size = log2n.ceil() as usize;
-        let mut blocks: Vec<Vec<u64>> = Vec::with_capacity(vec_size);
-        for i in 0..vec_size {
-            blocks.push(Vec::with_capacity(sub_vec_size));
-            for _ in 0..sub_vec_size {
-                blocks[i].push(0);
-            }
-        }
-        for i in 0..vec_size {
-            let mut count = 0;
-            for j in 0..(sub_vec_size - 1) {
-                for k in 0..block_size.to_usize() {
-                    count += bv
-                        .get_u8(i * super_block_size.to_usize() + j * block_size.to_usize() + k)
-                        as u64;
+    fn compute_counts(bv: &BitVec) -> Vec<u64> {
+        let mut counts = vec![];
+        RankSupport::fill_counts(bv, &mut counts);
+        counts
+    }
+    /// Refills `counts` for `bv`, reusing its allocation.
+    fn fill_counts(bv: &BitVec, counts: &mut Vec<u64>) {
+        let words = bv.words();
+        let super_blocks = words.len() / WORDS_PER_SUPER_BLOCK + 1;
+        counts.clear();
+        counts.reserve(2 * super_blocks);
+        let mut total = 0;
+        for sb in 0..super_blocks {
+            counts.push(total);
+            let mut relative = 0;
+            let mut packed = 0;
+            for k in 0..WORDS_PER_SUPER_BLOCK {
+                if k > 0 {
+                    packed |= relative << (BLOCK_COUNTER_WIDTH * (k - 1));
                 }
-                blocks[i][j + 1] = count;
-            }
-        }
-        // println!("Blocks: {:?}", blocks);
-        blocks
-            .into_iter()
-            .map(|v| {
-                v.into_iter()
-                    .map(|x| BitVec::from_u64(x.clone(), block_space.to_usize()))
-                    .collect()
-            })
-            .collect()
-    }
-    fn compute_rp(bv: &BitVec) -> Vec<Vec<BitVec>> {
-        let log2n = (bv.size as f64).log2();
-        let super_block_size = (log2n * log2n / 2.0).ceil() as usize;
-        let block_size = (log2n / 2.0).ceil() as usize;
-        let block_space = (super_block_size as f64).log2().ceil() as usize;
-        let lookup_table_size = 2_usize.pow(block_size as u32);
-        let mut lookup_table: Vec<Vec<u64>> = Vec::with_capacity(lookup_table_size);
-        for _ in 0..lookup_table_size {
-            lookup_table.push(Vec::with_capacity(block_size));
-        }
-        for i in 0..lookup_table_size {
-            for j in 0..block_size {
-                // println!("Round (i: {}) (j: {})", i, j);
-                let temp_bv = BitVec::from_u64(i as u64, block_size);
-                let rank = RankSupport::dummy_rankn(&temp_bv, j);
-                lookup_table[i].push(rank);
+                let w = sb * WORDS_PER_SUPER_BLOCK + k;
+                relative += words.get(w).map_or(0, |w| w.count_ones() as u64);
             }
+            counts.push(packed);
+            total += relative;
         }
-        // println!("Lookup: {:?}", lookup_table);
-        lookup_table
-            .into_iter()
-            .map(|v| {
-                v.into_iter()
-                    .map(|x| BitVec::from_u64(x, block_space))
-                    .collect()
-            })
-            .collect()
     }
     pub(in crate) fn compute_index(&mut self) {
-        self.rs = RankSupport::compute_rs(self.bv.borrow());
-        self.rb = RankSupport::compute_rb(self.bv.borrow(), &self.rs);
-        self.rp = RankSupport::compute_rp(self.bv.borrow());
+        RankSupport::fill_counts(self.bv.borrow(), &mut self.counts);
     }
-    pub(in crate) fn new_with_index_computation(bit_vec: Cow<'bv, BitVec>) -> RankSupport {
+    pub(in crate) fn new_with_index_computation(bit_vec: Cow<'bv, BitVec>) -> RankSupport<'bv> {
         let bv = bit_vec;
-        let rs = RankSupport::compute_rs(bv.borrow());
-        let rb = RankSupport::compute_rb(bv.borrow(), &rs);
-        let rp = RankSupport::compute_rp(bv.borrow());
-        RankSupport { bv, rs, rb, rp }
+        let counts = RankSupport::compute_counts(bv.borrow());
+        RankSupport { bv, counts }
     }
+    /// Sets bit `i` without updating the counters, which must be recomputed
+    /// before the next query, see [`RankSupport::update`]. Use a
+    /// [`crate::DynamicBitVec`] for edits mixed with queries.
     pub fn set(&mut self, i: usize) {
         self.bv.to_mut().set(i);
     }
+    /// Clears bit `i`, leaving the counters stale like [`RankSupport::set`].
+    pub fn clear(&mut self, i: usize) {
+        self.bv.to_mut().clear(i);
+    }
+    /// Number of ones in `[0, i)`, for `i <= size`.
+    #[inline]
+    pub(in crate) fn rank1_exclusive(&self, i: usize) -> u64 {
+        let i = i.min(self.bv.size);
+        let w = i / WORD_SIZE;
+        let sb = w / WORDS_PER_SUPER_BLOCK;
+        let k = w % WORDS_PER_SUPER_BLOCK;
+        let value_from_super_block = self.super_block_rank1(sb);
+        let value_from_block = self.block_rank1(sb, k);
+        let value_from_word = match self.bv.bv.get(w) {
+            Some(word) => (word & prefix_mask(i % WORD_SIZE)).count_ones() as u64,
+            None => 0,
+        };
+        value_from_super_block + value_from_block + value_from_word
+    }
+    /// Number of ones in the whole bit vector.
+    pub(in crate) fn count_ones(&self) -> u64 {
+        self.rank1_exclusive(self.bv.size)
+    }
+    /// Number of ones before superblock `sb`.
+    #[inline]
+    pub(in crate) fn super_block_rank1(&self, sb: usize) -> u64 {
+        self.counts[2 * sb]
+    }
+    /// Number of ones in the first `k < 8` words of superblock `sb`.
+    #[inline]
+    pub(in crate) fn block_rank1(&self, sb: usize, k: usize) -> u64 {
+        if k == 0 {
+            0
+        } else {
+            (self.counts[2 * sb + 1] >> (BLOCK_COUNTER_WIDTH * (k - 1))) & 0x1FF
+        }
+    }
+    /// Number of zeros before superblock `sb`.
+    #[inline]
+    pub(in crate) fn super_block_rank0(&self, sb: usize) -> u64 {
+        (sb * SUPER_BLOCK_SIZE) as u64 - self.super_block_rank1(sb)
+    }
+    /// Number of zeros in the first `k < 8` words of superblock `sb`.
+    #[inline]
+    pub(in crate) fn block_rank0(&self, sb: usize, k: usize) -> u64 {
+        (k * WORD_SIZE) as u64 - self.block_rank1(sb, k)
+    }
 }
 
 impl<'bv> RankSupport<'bv> /* Public API */ {
-    pub fn new(bit_vec: &BitVec) -> RankSupport {
+    pub fn new(bit_vec: &BitVec) -> RankSupport<'_> {
         RankSupport::new_with_index_computation(Cow::Borrowed(bit_vec))
     }
     pub fn dummy_rankn(bv: &BitVec, i: usize) -> u64 {
@@ -133,35 +129,31 @@ impl<'bv> RankSupport<'bv> /* Public API */ {
         }
         rank
     }
+    pub fn dummy_rank0n(bv: &BitVec, i: usize) -> u64 {
+        let mut rank = 0;
+        for ind in 0..(i + 1).min(bv.size) {
+            rank += !bv.get(ind) as u64;
+        }
+        rank
+    }
+    /// Number of ones in `[0, i]`.
     pub fn rank1(&self, i: u64) -> u64 {
-        let log2n = (self.bv.size as f64).log2();
-        let super_block_size = (log2n * log2n / 2.0).ceil() as usize;
-        let block_size = (log2n / 2.0).ceil() as usize;
-        // println!("SuperBlockSize: {}", super_block_size);
-        // println!("BlockSize: {}", block_size);
-        let super_block_index = (i as usize / super_block_size) as usize;
-        let i = i as usize % super_block_size;
-        let block_index = (i / block_size) as usize;
-        let lookup_rank_index = i % block_size;
-        let value_from_super_block = self.rs[super_block_index].to_u64();
-        let value_from_block = self.rb[super_block_index][block_index].to_u64();
-        let left = super_block_index * super_block_size + block_index * block_size;
-        let right = left + block_size;
-        let lookup_row_index = self.bv.extract(left, right).to_u64() as usize;
-        let value_from_lookup = self.rp[lookup_row_index][lookup_rank_index].to_u64();
-        // println!("Lookup:");
-        // print!("(left: {})\t", left);
-        // print!("(right: {})\t", right);
-        // print!("(extracted: {})\t", self.bv.extract(left, right));
-        // print!("(row: {})\t", lookup_row_index);
-        // println!("(rank: {})", lookup_rank_index);
-
-        value_from_super_block + value_from_block + value_from_lookup
+        self.rank1_exclusive((i as usize).saturating_add(1))
+    }
+    /// Number of zeros in `[0, i]`.
+    pub fn rank0(&self, i: u64) -> u64 {
+        let i = (i as usize).saturating_add(1).min(self.bv.size);
+        i as u64 - self.rank1_exclusive(i)
+    }
+    /// Edits the bit vector with `f`, for instance `|bv| *bv &= &mask`, then
+    /// recomputes the counters in place with one popcount pass over the words.
+    /// A borrowed bit vector is copied first.
+    pub fn update<F: FnOnce(&mut BitVec)>(&mut self, f: F) {
+        f(self.bv.to_mut());
+        self.compute_index();
     }
     pub fn overhead(&self) -> usize {
-        std::mem::size_of_val(&*self.rs)
-            + std::mem::size_of_val(&*self.rb)
-            + std::mem::size_of_val(&*self.rp)
+        std::mem::size_of_val(&*self.counts)
     }
     pub fn save(&self, file_name: &str) -> std::io::Result<()> {
         let serialized = serde_json::to_string(&self)?;
@@ -226,6 +218,37 @@ mod rank1_tests {
         }
     }
     #[test]
+    fn unaligned_size_tests() {
+        for size in (1..=1100).step_by(7) {
+            let b = BitVec::new_with_random(size);
+            let r = RankSupport::new(&b);
+            for j in 0..b.size {
+                let dummy_res = RankSupport::dummy_rankn(&b, j);
+                let smart_res = r.rank1(j as u64);
+                assert_eq!(
+                    dummy_res, smart_res,
+                    "<============= Case [size: {}, point: {}] Starts ==============>\n \
+                    BV = {}\n\
+                    Rank = {}\n\
+                    Dummy Rank = {}\n\
+                    <============= Case [size: {}, point: {}] Ends ==============>\n",
+                    size, j, b, smart_res, dummy_res, size, j
+                );
+            }
+        }
+    }
+    #[test]
+    fn dense_tests() {
+        for size in [511, 512, 513, 1024, 1500] {
+            let b = BitVec::new_with_words(vec![u64::MAX; size / 64 + 1], size);
+            let r = RankSupport::new(&b);
+            for j in 0..b.size {
+                assert_eq!(r.rank1(j as u64), j as u64 + 1);
+            }
+            assert_eq!(r.rank1(u64::MAX), size as u64);
+        }
+    }
+    #[test]
     fn medium_tests() {
         for i in 128..=160 {
             let size = i * 128;
@@ -331,6 +354,48 @@ mod rank1_tests {
             }
         }
     }
+    #[test]
+    fn update_tests() {
+        let b = BitVec::new_with_random(5000);
+        let mask = BitVec::new_with_random(5000);
+        let mut r = RankSupport::new(&b);
+        r.update(|bv| *bv &= &mask);
+        let and = &b & &mask;
+        for j in 0..and.size {
+            assert_eq!(r.rank1(j as u64), RankSupport::dummy_rankn(&and, j));
+        }
+        r.update(|bv| *bv = !&*bv);
+        assert_eq!(r.rank1(4999), and.count_zeros());
+        assert_eq!(r, RankSupport::new(&!&and));
+    }
+}
+
+#[cfg(test)]
+mod rank0_tests {
+    use crate::bit_vec::BitVec;
+    use crate::rank_support::RankSupport;
+    #[test]
+    fn small_tests() {
+        for size in (1..=1100).step_by(13) {
+            let b = BitVec::new_with_random(size);
+            let r = RankSupport::new(&b);
+            for j in 0..b.size {
+                let dummy_res = RankSupport::dummy_rank0n(&b, j);
+                let smart_res = r.rank0(j as u64);
+                assert_eq!(
+                    dummy_res, smart_res,
+                    "<============= Case [size: {}, point: {}] Starts ==============>\n \
+                    BV = {}\n\
+                    Rank0 = {}\n\
+                    Dummy Rank0 = {}\n\
+                    <============= Case [size: {}, point: {}] Ends ==============>\n",
+                    size, j, b, smart_res, dummy_res, size, j
+                );
+                assert_eq!(r.rank0(j as u64) + r.rank1(j as u64), j as u64 + 1);
+            }
+            assert_eq!(r.rank0(u64::MAX), b.count_zeros());
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,291 @@
+use crate::bit_vec::{BitVec, WORD_SIZE};
+use crate::elias_fano::EliasFano;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// Run-length encoded bit vector, for bits coming in long runs.
+///
+/// Run `j` of ones is described by where it starts and by the number of ones
+/// before it, both increasing with `j` and stored as [`EliasFano`] sequences.
+/// A query finds the run around a position with a rank on the starts, or the
+/// run holding the `i`-th one with a rank on the one counts, so space and time
+/// depend on the number of runs rather than on the length.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct RleBitVec {
+    size: usize,
+    ones: u64,
+    starts: EliasFano,
+    ones_before: EliasFano,
+}
+
+impl RleBitVec {
+    fn num_runs(&self) -> usize {
+        self.starts.len()
+    }
+    fn start(&self, j: usize) -> u64 {
+        self.starts.access(j).unwrap()
+    }
+    fn ones_before(&self, j: usize) -> u64 {
+        self.ones_before.access(j).unwrap()
+    }
+    fn run_len(&self, j: usize) -> u64 {
+        let next = if j + 1 < self.num_runs() {
+            self.ones_before(j + 1)
+        } else {
+            self.ones
+        };
+        next - self.ones_before(j)
+    }
+    /// Zeros before run `j`.
+    fn zeros_before(&self, j: usize) -> u64 {
+        self.start(j) - self.ones_before(j)
+    }
+}
+
+impl RleBitVec /* Public API */ {
+    pub fn new(bv: &BitVec) -> RleBitVec {
+        let (mut starts, mut ones_before) = (vec![], vec![]);
+        let mut ones = 0;
+        let mut pos = bv.run_end(0, false);
+        while pos < bv.len() {
+            let end = bv.run_end(pos, true);
+            starts.push(pos as u64);
+            ones_before.push(ones);
+            ones += (end - pos) as u64;
+            pos = bv.run_end(end, false);
+        }
+        RleBitVec {
+            size: bv.len(),
+            ones,
+            starts: EliasFano::new_with_universe(&starts, bv.len() as u64),
+            ones_before: EliasFano::new_with_universe(&ones_before, ones.max(1)),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.size
+    }
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+    pub fn count_ones(&self) -> u64 {
+        self.ones
+    }
+    pub fn get(&self, i: usize) -> bool {
+        match self.starts.rank1(i as u64) as usize {
+            0 => false,
+            j => i as u64 - self.start(j - 1) < self.run_len(j - 1),
+        }
+    }
+    /// Number of ones in `[0, i]`.
+    pub fn rank1(&self, i: u64) -> u64 {
+        let i = i.min(self.size as u64);
+        match self.starts.rank1(i) as usize {
+            0 => 0,
+            j => {
+                let inside = (i - self.start(j - 1) + 1).min(self.run_len(j - 1));
+                self.ones_before(j - 1) + inside
+            },
+        }
+    }
+    /// Number of zeros in `[0, i]`.
+    pub fn rank0(&self, i: u64) -> u64 {
+        let i = i.min(self.size as u64);
+        (i + 1).min(self.size as u64) - self.rank1(i)
+    }
+    /// Position of the `i`-th one, counting from one.
+    pub fn select1(&self, i: u64) -> Option<u64> {
+        if i == 0 || i > self.ones {
+            return None;
+        }
+        let j = self.ones_before.rank1(i - 1) as usize - 1;
+        Some(self.start(j) + i - 1 - self.ones_before(j))
+    }
+    /// Position of the `i`-th zero, counting from one.
+    pub fn select0(&self, i: u64) -> Option<u64> {
+        if i == 0 || i > self.size as u64 - self.ones {
+            return None;
+        }
+        // Runs with fewer than i zeros before them, by binary search.
+        let (mut lo, mut hi) = (0, self.num_runs());
+        while lo < hi {
+            let m = (lo + hi) / 2;
+            if self.zeros_before(m) < i {
+                lo = m + 1;
+            } else {
+                hi = m;
+            }
+        }
+        match lo {
+            0 => Some(i - 1),
+            j => {
+                let end = self.start(j - 1) + self.run_len(j - 1);
+                Some(end + i - 1 - self.zeros_before(j - 1))
+            },
+        }
+    }
+    /// Maximal runs as `(bit, length)`, in order.
+    pub fn runs(&self) -> impl Iterator<Item = (bool, usize)> + '_ {
+        let mut end = 0;
+        (0..=self.num_runs()).flat_map(move |j| {
+            let (start, len) = if j < self.num_runs() {
+                (self.start(j) as usize, self.run_len(j) as usize)
+            } else {
+                (self.size, 0)
+            };
+            let zeros = start - end;
+            end = start + len;
+            [(false, zeros), (true, len)]
+                .into_iter()
+                .filter(|(_, len)| *len > 0)
+        })
+    }
+    pub fn to_bit_vec(&self) -> BitVec {
+        let mut bv = BitVec::new(self.size);
+        for j in 0..self.num_runs() {
+            let mut pos = self.start(j) as usize;
+            let mut len = self.run_len(j) as usize;
+            while len > 0 {
+                let width = len.min(WORD_SIZE);
+                bv.set_bits(pos, width, u64::MAX);
+                pos += width;
+                len -= width;
+            }
+        }
+        bv
+    }
+    pub fn overhead(&self) -> usize {
+        self.starts.overhead() + self.ones_before.overhead()
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<RleBitVec> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: RleBitVec = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod rle_bit_vec_tests {
+    use crate::bit_vec::BitVec;
+    use crate::rank_support::RankSupport;
+    use crate::rle_bit_vec::RleBitVec;
+    use crate::select_support::SelectSupport;
+    use rand::Rng;
+    use std::borrow::Cow;
+
+    /// Alternating runs of random lengths up to `max_run`.
+    fn random_runs(size: usize, max_run: usize, first: bool) -> BitVec {
+        let mut rng = rand::thread_rng();
+        let mut bv = BitVec::new(size);
+        let (mut pos, mut bit) = (0, first);
+        while pos < size {
+            let len = rng.gen_range(1..=max_run).min(size - pos);
+            if bit {
+                for i in pos..pos + len {
+                    bv.set(i);
+                }
+            }
+            pos += len;
+            bit = !bit;
+        }
+        bv
+    }
+
+    #[test]
+    fn rank_select_tests() {
+        for (size, max_run) in [(1, 1), (10, 3), (1000, 1), (5000, 50), (20000, 700)] {
+            for first in [false, true] {
+                let bv = random_runs(size, max_run, first);
+                let rle = RleBitVec::new(&bv);
+                let r = RankSupport::new(&bv);
+                let s = SelectSupport::new(Cow::Borrowed(&r));
+                assert_eq!(rle.count_ones(), bv.count_ones());
+                for i in 0..size {
+                    assert_eq!(rle.get(i), bv.get(i));
+                    assert_eq!(rle.rank1(i as u64), r.rank1(i as u64), "rank1 at {}", i);
+                    assert_eq!(rle.rank0(i as u64), r.rank0(i as u64));
+                }
+                assert_eq!(rle.rank1(size as u64 + 3), r.rank1(size as u64 + 3));
+                assert_eq!(rle.rank1(u64::MAX), bv.count_ones());
+                assert_eq!(rle.rank0(u64::MAX), bv.count_zeros());
+                for k in 0..=bv.count_ones() + 1 {
+                    assert_eq!(rle.select1(k), s.select1(k), "select1 {}", k);
+                }
+                for k in 0..=bv.count_zeros() + 1 {
+                    assert_eq!(rle.select0(k), s.select0(k), "select0 {}", k);
+                }
+            }
+        }
+    }
+    #[test]
+    fn clustered_runs_tests() {
+        // Every run starts in the first few thousand bits of a long vector, so
+        // they all share one Elias-Fano bucket.
+        let mut bv = random_runs(4000, 3, true);
+        bv.resize(1 << 20, false);
+        bv.set((1 << 20) - 1);
+        let rle = RleBitVec::new(&bv);
+        let r = RankSupport::new(&bv);
+        let s = SelectSupport::new(Cow::Borrowed(&r));
+        for i in (0..4100).chain([500_000, (1 << 20) - 2, (1 << 20) - 1]) {
+            assert_eq!(rle.get(i), bv.get(i));
+            assert_eq!(rle.rank1(i as u64), r.rank1(i as u64), "rank1 at {}", i);
+        }
+        for k in 0..=bv.count_ones() + 1 {
+            assert_eq!(rle.select1(k), s.select1(k), "select1 {}", k);
+        }
+    }
+    #[test]
+    fn conversion_tests() {
+        for bv in [
+            BitVec::new(0),
+            BitVec::new(100),
+            random_runs(3000, 200, true),
+            BitVec::new_with_random(777),
+        ] {
+            let rle = RleBitVec::new(&bv);
+            assert_eq!(rle.to_bit_vec(), bv);
+            let mut pos = 0;
+            let mut last = None;
+            for (bit, len) in rle.runs() {
+                assert!(len > 0 && last != Some(bit));
+                for i in pos..pos + len {
+                    assert_eq!(bv.get(i), bit);
+                }
+                pos += len;
+                last = Some(bit);
+            }
+            assert_eq!(pos, bv.len());
+        }
+        let mut ones = BitVec::new(130);
+        for i in 0..130 {
+            ones.set(i);
+        }
+        let rle = RleBitVec::new(&ones);
+        assert_eq!(rle.runs().collect::<Vec<_>>(), vec![(true, 130)]);
+        assert_eq!(rle.select0(1), None);
+    }
+    #[test]
+    fn space_test() {
+        let bv = random_runs(1 << 20, 5000, false);
+        let rle = RleBitVec::new(&bv);
+        assert!(rle.overhead() * 20 < std::mem::size_of_val(bv.words()));
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let bv = random_runs(2000, 30, true);
+        let rle = RleBitVec::new(&bv);
+        rle.save("example_rle_bit_vec.txt")?;
+        let rle2 = RleBitVec::load("example_rle_bit_vec.txt".to_owned())?;
+        assert_eq!(rle2.to_bit_vec(), bv);
+        Ok(())
+    }
+}
@@ -0,0 +1,398 @@
+use crate::bit_vec::BitVec;
+use crate::int_vec::{bit_width, IntVec};
+use crate::select_support::SelectSupport;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// Parentheses per block, each summarized by its minimum excess.
+const BLOCK_SIZE: usize = 512;
+/// Blocks per superblock, with a sparse table over the blocks of each one.
+const BLOCKS_PER_SUPER_BLOCK: usize = 32;
+/// Levels of the in-superblock tables, windows of 2 to 16 blocks; a window of
+/// the whole superblock is its entry in the superblock table.
+const SUPER_BLOCK_LEVELS: usize = 4;
+
+/// For every byte read most significant bit first: its excess change, the
+/// minimum excess after 1 to 8 of its bits, and the last such count of bits
+/// reaching that minimum.
+const BYTE_EXCESS: [(i8, i8, u8); 256] = byte_excess_table();
+
+const fn byte_excess_table() -> [(i8, i8, u8); 256] {
+    let mut table = [(0, 0, 0); 256];
+    let mut v = 0;
+    while v < 256 {
+        let (mut e, mut min, mut pos) = (0, i8::MAX, 0);
+        let mut j = 0;
+        while j < 8 {
+            e += if (v >> (7 - j)) & 1 == 1 { 1 } else { -1 };
+            if e <= min {
+                min = e;
+                pos = j as u8 + 1;
+            }
+            j += 1;
+        }
+        table[v] = (e, min, pos);
+        v += 1;
+    }
+    table
+}
+
+/// Range minimum queries over an array that is not kept, in `2n + 2` bits of
+/// balanced parentheses plus `o(n)`-sized indexes.
+///
+/// The tree has a virtual root, and position `i` of the array as its node of
+/// preorder rank `i + 1`, hanging from the closest earlier position holding a
+/// smaller or equal value. The leftmost minimum of a range is then the last
+/// node of least depth between the nodes of its ends, which is where the
+/// excess of the parentheses between them is last at its minimum.
+///
+/// That minimum is found in constant time. Every block of `BLOCK_SIZE`
+/// parentheses stores its minimum excess relative to its start and where that
+/// minimum last occurs, in 10 and 9 bits. Whole blocks of a range are covered
+/// by sparse tables over these minima: one per superblock of 32 blocks with
+/// 4-bit offsets, and one over the superblocks. The partial blocks at both
+/// ends are scanned a byte at a time with a lookup table.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rmq {
+    parens: SelectSupport<'static>,
+    /// Per block, `E(start) + 1 - min` where `min` is the least `E(k)` for `k`
+    /// in `(start, end]`.
+    block_depth: IntVec,
+    /// Per block, `k - start - 1` for the last `k` reaching that minimum.
+    block_pos: IntVec,
+    /// For block `b` and level `j` in `1..=4`, at `4 * b + j - 1`, the offset
+    /// from `b` of the best block in `[b, b + 2^j)`, within its superblock.
+    in_super_block: IntVec,
+    /// Level `j` holds, for superblock `s`, the best block of superblocks
+    /// `[s, s + 2^j)`.
+    super_blocks: Vec<IntVec>,
+}
+
+impl Rmq {
+    fn bits(&self) -> &BitVec {
+        &self.parens.r.bv
+    }
+    /// Excess of the first `k` parentheses.
+    fn excess(&self, k: usize) -> i64 {
+        2 * self.parens.r.rank1_exclusive(k) as i64 - k as i64
+    }
+    /// Least `E(k)` for `k` in `(from, to]` and the last `k` reaching it, a
+    /// byte at a time where aligned.
+    fn scan(&self, from: usize, to: usize) -> (i64, usize) {
+        let bits = self.bits();
+        let mut e = self.excess(from);
+        let (mut min, mut pos) = (i64::MAX, from);
+        let mut p = from;
+        while p < to {
+            if p.is_multiple_of(8) && p + 8 <= to {
+                let (delta, m, at) = BYTE_EXCESS[bits.get_bits(p, 8) as usize];
+                if e + m as i64 <= min {
+                    min = e + m as i64;
+                    pos = p + at as usize;
+                }
+                e += delta as i64;
+                p += 8;
+            } else {
+                e += if bits.get(p) { 1 } else { -1 };
+                p += 1;
+                if e <= min {
+                    min = e;
+                    pos = p;
+                }
+            }
+        }
+        (min, pos)
+    }
+    fn block_min(&self, b: usize) -> i64 {
+        self.excess(b * BLOCK_SIZE) + 1 - self.block_depth.get(b).unwrap() as i64
+    }
+    fn block_argmin(&self, b: usize) -> usize {
+        b * BLOCK_SIZE + 1 + self.block_pos.get(b).unwrap() as usize
+    }
+    /// Of blocks `x <= y`, the one with the smaller minimum, `y` on ties.
+    fn pick(&self, x: usize, y: usize) -> usize {
+        if self.block_min(y) <= self.block_min(x) {
+            y
+        } else {
+            x
+        }
+    }
+    fn in_super_block_best(&self, b: usize, level: usize) -> usize {
+        let k = SUPER_BLOCK_LEVELS * b + level - 1;
+        b + self.in_super_block.get(k).unwrap() as usize
+    }
+    /// Best block in `[l, r)`, non-empty and within one superblock.
+    fn best_in_super_block(&self, l: usize, r: usize) -> usize {
+        let len = r - l;
+        if len == BLOCKS_PER_SUPER_BLOCK {
+            return self.super_blocks[0]
+                .get(l / BLOCKS_PER_SUPER_BLOCK)
+                .unwrap() as usize;
+        }
+        match len.ilog2() as usize {
+            0 => l,
+            j => self.pick(
+                self.in_super_block_best(l, j),
+                self.in_super_block_best(r - (1 << j), j),
+            ),
+        }
+    }
+    /// Best block in superblocks `[l, r)`, non-empty.
+    fn best_in_super_blocks(&self, l: usize, r: usize) -> usize {
+        let j = (r - l).ilog2() as usize;
+        let table = &self.super_blocks[j];
+        self.pick(
+            table.get(l).unwrap() as usize,
+            table.get(r - (1 << j)).unwrap() as usize,
+        )
+    }
+    /// Best block in `[l, r)`, non-empty.
+    fn best_block(&self, l: usize, r: usize) -> usize {
+        let (sl, sr) = (l / BLOCKS_PER_SUPER_BLOCK, (r - 1) / BLOCKS_PER_SUPER_BLOCK);
+        if sl == sr {
+            return self.best_in_super_block(l, r);
+        }
+        let mut best = self.best_in_super_block(l, (sl + 1) * BLOCKS_PER_SUPER_BLOCK);
+        if sl + 1 < sr {
+            best = self.pick(best, self.best_in_super_blocks(sl + 1, sr));
+        }
+        self.pick(
+            best,
+            self.best_in_super_block(sr * BLOCKS_PER_SUPER_BLOCK, r),
+        )
+    }
+    /// Last `k` in `[a, b]` where `E(k)` is smallest.
+    fn rightmost_min_excess(&self, a: usize, b: usize) -> usize {
+        let mut best = (self.excess(a), a);
+        let mut take = |(m, k): (i64, usize)| {
+            if m <= best.0 {
+                best = (m, k);
+            }
+        };
+        let head_end = b.min(a.next_multiple_of(BLOCK_SIZE));
+        if a < head_end {
+            take(self.scan(a, head_end));
+        }
+        let (bl, br) = (head_end / BLOCK_SIZE, b / BLOCK_SIZE);
+        if head_end < b && bl < br {
+            let block = self.best_block(bl, br);
+            take((self.block_min(block), self.block_argmin(block)));
+        }
+        let tail_start = head_end.max(br * BLOCK_SIZE);
+        if tail_start < b {
+            take(self.scan(tail_start, b));
+        }
+        best.1
+    }
+    /// Fills the block minima and the sparse tables over them.
+    fn compute_index(&mut self) {
+        let size = self.bits().len();
+        let blocks = size.div_ceil(BLOCK_SIZE);
+        self.block_depth = IntVec::new_with_len(blocks, bit_width(BLOCK_SIZE as u64 + 1));
+        self.block_pos = IntVec::new_with_len(blocks, bit_width(BLOCK_SIZE as u64 - 1));
+        for b in 0..blocks {
+            let start = b * BLOCK_SIZE;
+            let (min, pos) = self.scan(start, (start + BLOCK_SIZE).min(size));
+            let depth = self.excess(start) + 1 - min;
+            self.block_depth.set(b, depth as u64);
+            self.block_pos.set(b, (pos - start - 1) as u64);
+        }
+        let offset_width = bit_width(BLOCKS_PER_SUPER_BLOCK as u64 / 2 - 1);
+        self.in_super_block = IntVec::new_with_len(SUPER_BLOCK_LEVELS * blocks, offset_width);
+        for j in 1..=SUPER_BLOCK_LEVELS {
+            for b in 0..blocks {
+                let half = 1 << (j - 1);
+                let end = (b / BLOCKS_PER_SUPER_BLOCK + 1) * BLOCKS_PER_SUPER_BLOCK;
+                let left = if j == 1 {
+                    b
+                } else {
+                    self.in_super_block_best(b, j - 1)
+                };
+                let best = match b + half {
+                    m if m >= end.min(blocks) => left,
+                    m if j == 1 => self.pick(left, m),
+                    m => self.pick(left, self.in_super_block_best(m, j - 1)),
+                };
+                self.in_super_block
+                    .set(SUPER_BLOCK_LEVELS * b + j - 1, (best - b) as u64);
+            }
+        }
+        let supers = blocks.div_ceil(BLOCKS_PER_SUPER_BLOCK);
+        let width = bit_width(blocks.saturating_sub(1) as u64);
+        let firsts: Vec<u64> = (0..supers)
+            .map(|s| {
+                let l = s * BLOCKS_PER_SUPER_BLOCK;
+                let r = (l + BLOCKS_PER_SUPER_BLOCK).min(blocks);
+                (l + 1..r).fold(l, |best, b| self.pick(best, b)) as u64
+            })
+            .collect();
+        self.super_blocks = vec![IntVec::new_with_values_and_width(&firsts, width)];
+        let mut j = 1;
+        while 1 << j <= supers {
+            let prev = &self.super_blocks[j - 1];
+            let level: Vec<u64> = (0..supers + 1 - (1 << j))
+                .map(|s| {
+                    let (x, y) = (prev.get(s).unwrap(), prev.get(s + (1 << (j - 1))).unwrap());
+                    self.pick(x as usize, y as usize) as u64
+                })
+                .collect();
+            self.super_blocks
+                .push(IntVec::new_with_values_and_width(&level, width));
+            j += 1;
+        }
+    }
+}
+
+impl Rmq /* Public API */ {
+    pub fn new<T: Ord>(values: &[T]) -> Rmq {
+        let n = values.len();
+        let mut parens = BitVec::new(2 * n + 2);
+        // The zeros are already in place: closing a node only moves past it.
+        parens.set(0);
+        let mut pos = 1;
+        let mut stack: Vec<usize> = vec![];
+        for (i, value) in values.iter().enumerate() {
+            while stack.last().is_some_and(|top| values[*top] > *value) {
+                stack.pop();
+                pos += 1;
+            }
+            parens.set(pos);
+            pos += 1;
+            stack.push(i);
+        }
+        let mut rmq = Rmq {
+            parens: SelectSupport::new_with_bit_vec(parens),
+            block_depth: IntVec::new(0),
+            block_pos: IntVec::new(0),
+            in_super_block: IntVec::new(0),
+            super_blocks: vec![],
+        };
+        rmq.compute_index();
+        rmq
+    }
+    pub fn len(&self) -> usize {
+        self.bits().len() / 2 - 1
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Position of the leftmost minimum in `[l, r)`.
+    pub fn rmq(&self, l: usize, r: usize) -> Option<usize> {
+        let r = r.min(self.len());
+        if l >= r {
+            return None;
+        }
+        // Position i is the node of preorder rank i + 1, the (i + 2)-th one.
+        let x = self.parens.select1(l as u64 + 2).unwrap() as usize;
+        let y = self.parens.select1(r as u64 + 1).unwrap() as usize;
+        let m = self.rightmost_min_excess(x, y);
+        Some(self.parens.r.rank1_exclusive(m) as usize - 1)
+    }
+    /// Bytes used on top of the `2n + 2` parentheses.
+    pub fn overhead(&self) -> usize {
+        self.parens.overhead()
+            + self.block_depth.overhead()
+            + self.block_pos.overhead()
+            + self.in_super_block.overhead()
+            + self
+                .super_blocks
+                .iter()
+                .map(|t| t.overhead())
+                .sum::<usize>()
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<Rmq> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: Rmq = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod rmq_tests {
+    use crate::rmq::Rmq;
+    use rand::Rng;
+
+    fn naive_rmq(values: &[u32], l: usize, r: usize) -> Option<usize> {
+        (l..r).min_by_key(|i| values[*i])
+    }
+
+    #[test]
+    fn rmq_tests() {
+        let mut rng = rand::thread_rng();
+        for (n, max) in [(1, 10), (10, 3), (100, 1000), (2000, 5), (5000, u32::MAX)] {
+            let values: Vec<u32> = (0..n).map(|_| rng.gen_range(0..max)).collect();
+            let rmq = Rmq::new(&values);
+            assert_eq!(rmq.len(), n);
+            for _ in 0..2000 {
+                let l = rng.gen_range(0..n);
+                let r = rng.gen_range(l + 1..=n);
+                assert_eq!(rmq.rmq(l, r), naive_rmq(&values, l, r), "[{}, {})", l, r);
+            }
+            assert_eq!(rmq.rmq(0, n), naive_rmq(&values, 0, n));
+            assert_eq!(rmq.rmq(n, n + 1), None);
+        }
+    }
+    #[test]
+    fn monotone_tests() {
+        let increasing: Vec<u32> = (0..3000).collect();
+        let decreasing: Vec<u32> = (0..3000).rev().collect();
+        let constant = vec![7_u32; 3000];
+        let (inc, dec, cst) = (
+            Rmq::new(&increasing),
+            Rmq::new(&decreasing),
+            Rmq::new(&constant),
+        );
+        for (l, r) in [(0, 3000), (5, 6), (100, 2999), (1234, 2345)] {
+            assert_eq!(inc.rmq(l, r), Some(l));
+            assert_eq!(dec.rmq(l, r), Some(r - 1));
+            assert_eq!(cst.rmq(l, r), Some(l));
+        }
+        assert!(Rmq::new::<u32>(&[]).is_empty());
+    }
+    #[test]
+    fn long_range_tests() {
+        // Ranges spanning many superblocks, with minima planted at block edges.
+        let mut rng = rand::thread_rng();
+        let n = 200_000;
+        let mut values: Vec<u32> = (0..n).map(|_| rng.gen_range(10..1000)).collect();
+        for i in [0, 255, 256, 8191, 8192, 77_777, n - 1] {
+            values[i] = rng.gen_range(0..3);
+        }
+        let rmq = Rmq::new(&values);
+        for _ in 0..3000 {
+            let l = rng.gen_range(0..n);
+            let r = (l + rng.gen_range(1..n)).min(n);
+            assert_eq!(rmq.rmq(l, r), naive_rmq(&values, l, r), "[{}, {})", l, r);
+        }
+        for (l, r) in [(0, n), (1, n), (256, 8192), (257, 8193), (8000, 100_000)] {
+            assert_eq!(rmq.rmq(l, r), naive_rmq(&values, l, r), "[{}, {})", l, r);
+        }
+    }
+    #[test]
+    fn space_test() {
+        let n = 1 << 20;
+        let values: Vec<u32> = (0..n).map(|_| rand::random()).collect();
+        let rmq = Rmq::new(&values);
+        // The parentheses take 2n bits; the indexes stay below n bits.
+        assert!(rmq.overhead() * 8 < n);
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let values = [5, 3, 8, 3, 1, 9, 2];
+        let rmq = Rmq::new(&values);
+        rmq.save("example_rmq.txt")?;
+        let rmq2 = Rmq::load("example_rmq.txt".to_owned())?;
+        assert_eq!(rmq2.rmq(0, 4), Some(1));
+        assert_eq!(rmq2.rmq(5, 7), Some(6));
+        Ok(())
+    }
+}
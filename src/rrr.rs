@@ -0,0 +1,296 @@
+use crate::bit_vec::BitVec;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// Bits per RRR block.
+const BLOCK_SIZE: usize = 15;
+/// Bits used to store the class (number of ones) of a block.
+const CLASS_WIDTH: usize = 4;
+/// Blocks between two rank and offset pointer samples.
+const SAMPLE_RATE: usize = 32;
+
+const fn binomial_table() -> [[u16; BLOCK_SIZE + 1]; BLOCK_SIZE + 1] {
+    let mut table = [[0; BLOCK_SIZE + 1]; BLOCK_SIZE + 1];
+    let mut n = 0;
+    while n <= BLOCK_SIZE {
+        table[n][0] = 1;
+        let mut k = 1;
+        while k <= n {
+            table[n][k] = table[n - 1][k - 1] + if k < n { table[n - 1][k] } else { 0 };
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+}
+
+/// `BINOMIAL[n][k]` is `n` choose `k`.
+const BINOMIAL: [[u16; BLOCK_SIZE + 1]; BLOCK_SIZE + 1] = binomial_table();
+
+/// Bits needed to store the offset of a block of class `c`.
+fn offset_width(c: usize) -> usize {
+    let combinations = BINOMIAL[BLOCK_SIZE][c] as u64;
+    (u64::BITS - (combinations - 1).leading_zeros()) as usize
+}
+
+/// Rank of a `BLOCK_SIZE` bit block among the blocks with the same number of
+/// ones, in lexicographic order.
+fn encode_block(block: u64, c: usize) -> u64 {
+    let mut offset = 0;
+    let mut remaining = c;
+    for i in (0..BLOCK_SIZE).rev() {
+        if remaining == 0 {
+            break;
+        }
+        if block & (1 << i) != 0 {
+            offset += BINOMIAL[i][remaining] as u64;
+            remaining -= 1;
+        }
+    }
+    offset
+}
+
+fn decode_block(offset: u64, c: usize) -> u64 {
+    let mut block = 0;
+    let mut offset = offset;
+    let mut remaining = c;
+    for i in (0..BLOCK_SIZE).rev() {
+        if remaining == 0 {
+            break;
+        }
+        let skipped = BINOMIAL[i][remaining] as u64;
+        if offset >= skipped {
+            block |= 1 << i;
+            offset -= skipped;
+            remaining -= 1;
+        }
+    }
+    block
+}
+
+/// Raman-Raman-Rao compressed bit vector.
+///
+/// The bits are cut into blocks of 15. Each block is stored as its class, the
+/// number of ones it holds, and its offset, the rank of the block among all
+/// blocks of that class, in `⌈log C(15, class)⌉` bits. Every 32 blocks the
+/// number of ones so far and the position of the next offset are sampled, so a
+/// query decodes at most 32 classes and a single block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RrrBitVec {
+    size: usize,
+    classes: BitVec,
+    offsets: BitVec,
+    rank_samples: Vec<u64>,
+    offset_samples: Vec<u64>,
+}
+
+impl RrrBitVec {
+    fn class(&self, block: usize) -> usize {
+        self.classes.get_bits(block * CLASS_WIDTH, CLASS_WIDTH) as usize
+    }
+    fn num_blocks(&self) -> usize {
+        self.size.div_ceil(BLOCK_SIZE)
+    }
+    /// Ones before `block` and the decoded `block` itself, most significant bit
+    /// first.
+    fn seek(&self, block: usize) -> (u64, u64) {
+        let sample = block / SAMPLE_RATE;
+        let mut rank = self.rank_samples[sample];
+        let mut pointer = self.offset_samples[sample] as usize;
+        for b in sample * SAMPLE_RATE..block {
+            let c = self.class(b);
+            rank += c as u64;
+            pointer += offset_width(c);
+        }
+        let c = self.class(block);
+        let width = offset_width(c);
+        (rank, decode_block(self.offsets.get_bits(pointer, width), c))
+    }
+}
+
+impl RrrBitVec /* Public API */ {
+    pub fn new(bv: &BitVec) -> RrrBitVec {
+        let size = bv.len();
+        let num_blocks = size.div_ceil(BLOCK_SIZE);
+        let mut classes = BitVec::new(num_blocks * CLASS_WIDTH);
+        let mut offsets = vec![];
+        let mut offset_size = 0;
+        let mut rank_samples = vec![];
+        let mut offset_samples = vec![];
+        let mut rank = 0;
+        for block in 0..num_blocks {
+            if block % SAMPLE_RATE == 0 {
+                rank_samples.push(rank);
+                offset_samples.push(offset_size as u64);
+            }
+            let bits = bv.get_bits(block * BLOCK_SIZE, BLOCK_SIZE);
+            let c = bits.count_ones() as usize;
+            classes.set_bits(block * CLASS_WIDTH, CLASS_WIDTH, c as u64);
+            offsets.push((encode_block(bits, c), offset_width(c)));
+            offset_size += offset_width(c);
+            rank += c as u64;
+        }
+        if rank_samples.is_empty() {
+            rank_samples.push(0);
+            offset_samples.push(0);
+        }
+        let mut offset_bits = BitVec::new(offset_size);
+        let mut pointer = 0;
+        for (offset, width) in offsets {
+            offset_bits.set_bits(pointer, width, offset);
+            pointer += width;
+        }
+        RrrBitVec {
+            size,
+            classes,
+            offsets: offset_bits,
+            rank_samples,
+            offset_samples,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.size
+    }
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+    pub fn get(&self, i: usize) -> bool {
+        if i >= self.size {
+            return false;
+        }
+        let (_, block) = self.seek(i / BLOCK_SIZE);
+        block & (1 << (BLOCK_SIZE - 1 - i % BLOCK_SIZE)) != 0
+    }
+    /// Number of ones in `[0, i]`.
+    pub fn rank1(&self, i: u64) -> u64 {
+        if self.size == 0 {
+            return 0;
+        }
+        let i = (i as usize).min(self.size - 1);
+        let (rank, block) = self.seek(i / BLOCK_SIZE);
+        let prefix = BLOCK_SIZE - 1 - i % BLOCK_SIZE;
+        rank + (block >> prefix).count_ones() as u64
+    }
+    /// Number of zeros in `[0, i]`.
+    pub fn rank0(&self, i: u64) -> u64 {
+        i.saturating_add(1).min(self.size as u64) - self.rank1(i)
+    }
+    /// Position of the `i`-th one, counting from one.
+    pub fn select1(&self, i: u64) -> Option<u64> {
+        if i == 0 || self.size == 0 || i > self.rank1(self.size as u64 - 1) {
+            return None;
+        }
+        // Last sample with fewer than i ones before it.
+        let sample = self.rank_samples.partition_point(|r| *r < i) - 1;
+        let mut rank = self.rank_samples[sample];
+        let mut pointer = self.offset_samples[sample] as usize;
+        let mut block = sample * SAMPLE_RATE;
+        loop {
+            let c = self.class(block);
+            if rank + c as u64 >= i {
+                let bits = decode_block(self.offsets.get_bits(pointer, offset_width(c)), c);
+                let mut remaining = i - rank;
+                for j in 0..BLOCK_SIZE {
+                    if bits & (1 << (BLOCK_SIZE - 1 - j)) != 0 {
+                        remaining -= 1;
+                        if remaining == 0 {
+                            return Some((block * BLOCK_SIZE + j) as u64);
+                        }
+                    }
+                }
+            }
+            rank += c as u64;
+            pointer += offset_width(c);
+            block += 1;
+            debug_assert!(block < self.num_blocks());
+        }
+    }
+    /// Total bytes used by the classes, offsets and samples, to be compared
+    /// with the bit vector plus [`RankSupport::overhead`](crate::RankSupport::overhead).
+    pub fn overhead(&self) -> usize {
+        std::mem::size_of_val(self.classes.words())
+            + std::mem::size_of_val(self.offsets.words())
+            + std::mem::size_of_val(&*self.rank_samples)
+            + std::mem::size_of_val(&*self.offset_samples)
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<RrrBitVec> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: RrrBitVec = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod rrr_tests {
+    use crate::bit_vec::BitVec;
+    use crate::rank_support::RankSupport;
+    use crate::rrr::{decode_block, encode_block, RrrBitVec};
+    use crate::select_support::SelectSupport;
+    use std::borrow::Cow;
+
+    fn sparse_bit_vec(size: usize, density: f64) -> BitVec {
+        let mut b = BitVec::new(size);
+        for i in 0..size {
+            if rand::random::<f64>() < density {
+                b.set(i);
+            }
+        }
+        b
+    }
+
+    #[test]
+    fn block_codec_tests() {
+        for block in 0..(1_u64 << 15) {
+            let c = block.count_ones() as usize;
+            assert_eq!(decode_block(encode_block(block, c), c), block);
+        }
+    }
+    #[test]
+    fn get_rank_select_tests() {
+        for size in [1, 14, 15, 16, 479, 480, 481, 2000] {
+            for density in [0.0, 0.05, 0.5, 0.95, 1.0] {
+                let b = sparse_bit_vec(size, density);
+                let r = RankSupport::new(&b);
+                let s = SelectSupport::new(Cow::Borrowed(&r));
+                let rrr = RrrBitVec::new(&b);
+                for j in 0..size {
+                    assert_eq!(rrr.get(j), b.get(j));
+                    assert_eq!(rrr.rank1(j as u64), r.rank1(j as u64));
+                    assert_eq!(rrr.rank0(j as u64), r.rank0(j as u64));
+                }
+                assert_eq!(rrr.rank0(u64::MAX), r.rank0(u64::MAX));
+                for j in 0..=size as u64 + 1 {
+                    assert_eq!(rrr.select1(j), s.select1(j), "size {} select {}", size, j);
+                }
+            }
+        }
+    }
+    #[test]
+    fn overhead_tests() {
+        let size = 1 << 16;
+        let sparse = sparse_bit_vec(size, 0.01);
+        let plain = std::mem::size_of_val(sparse.words()) + RankSupport::new(&sparse).overhead();
+        assert!(RrrBitVec::new(&sparse).overhead() < plain / 2);
+        let dense = BitVec::new_with_random(size);
+        let plain = std::mem::size_of_val(dense.words()) + RankSupport::new(&dense).overhead();
+        assert!(RrrBitVec::new(&dense).overhead() < 2 * plain);
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let b = sparse_bit_vec(1000, 0.2);
+        let rrr = RrrBitVec::new(&b);
+        rrr.save("example_rrr.txt")?;
+        let rrr2 = RrrBitVec::load("example_rrr.txt".to_owned())?;
+        assert_eq!(rrr, rrr2);
+        Ok(())
+    }
+}
@@ -1,37 +1,103 @@
 #![allow(dead_code)]
-use crate::rank_support::RankSupport;
+use crate::bit_vec::{prefix_mask, select_in_word, BitVec, WORD_SIZE};
+use crate::int_vec::{bit_width, IntVec};
+use crate::rank_support::{RankSupport, SUPER_BLOCK_SIZE, WORDS_PER_SUPER_BLOCK};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::fs::File;
 use std::io::{Read, Write};
 
+/// Default number of ones between two select samples.
+pub(in crate) const DEFAULT_SAMPLE_RATE: usize = 512;
+
+/// Select over a [`RankSupport`], sampling the position of every
+/// `sample_rate`-th one and, separately, of every `sample_rate`-th zero. A
+/// query jumps to the sample preceding the answer, binary searches the rank
+/// superblocks between it and the next sample, scans the packed word counters
+/// of the superblock and finishes with an in-word select.
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct SelectSupport<'bv> {
     pub(crate) r: Cow<'bv, RankSupport<'bv>>,
+    pub(crate) sample_rate: usize,
+    pub(crate) samples1: IntVec,
+    pub(crate) samples0: IntVec,
 }
 
 impl<'bv> SelectSupport<'bv> {
-    fn binary_search_select1(&self, l: usize, r: usize, i: u64) -> u64 {
-        if l == r && i == 1 {
-            return l as u64;
-        }
-        let m = ((l + r) / 2) as u64;
-        let rank_0 = self.r.rank1(m);
-        let rank_1 = self.r.rank1(m + 1);
-        if rank_0 == (i - 1) && rank_1 == i {
-            m + 1
-        } else if rank_1 < i {
-            self.binary_search_select1(m as usize, r, i)
-        } else {
-            self.binary_search_select1(l, m as usize, i)
+    /// Positions of the 1st, `(sample_rate + 1)`-th, ... one (or zero), packed
+    /// on as many bits as the largest position needs.
+    fn compute_samples(r: &RankSupport, sample_rate: usize, ones: bool) -> IntVec {
+        let size = r.bv.size;
+        let mut samples = IntVec::new(bit_width(size as u64));
+        let mut count = 0;
+        let mut next = 1;
+        for (w, word) in r.bv.words().iter().enumerate() {
+            let word = if ones {
+                *word
+            } else {
+                !word & prefix_mask((size - w * WORD_SIZE).min(WORD_SIZE))
+            };
+            let c = word.count_ones() as u64;
+            while next <= count + c {
+                let pos = w * WORD_SIZE + select_in_word(word, (next - count - 1) as u32);
+                samples.push(pos as u64);
+                next += sample_rate as u64;
+            }
+            count += c;
+        }
+        samples
+    }
+    /// Position of the `i`-th one (or zero), `i` being at least one and at most
+    /// the number of ones (or zeros).
+    fn sampled_select(&self, i: u64, ones: bool) -> u64 {
+        type SuperBlockRank<'a> = fn(&RankSupport<'a>, usize) -> u64;
+        type BlockRank<'a> = fn(&RankSupport<'a>, usize, usize) -> u64;
+        let (samples, super_block_rank, block_rank): (_, SuperBlockRank<'bv>, BlockRank<'bv>) =
+            if ones {
+                (&self.samples1, RankSupport::super_block_rank1, RankSupport::block_rank1)
+            } else {
+                (&self.samples0, RankSupport::super_block_rank0, RankSupport::block_rank0)
+            };
+        let j = ((i - 1) / self.sample_rate as u64) as usize;
+        let size = self.r.bv.size as u64;
+        let start = samples.get(j).unwrap();
+        let end = samples.get(j + 1).unwrap_or(size - 1);
+        // Last superblock in [lo, hi] with fewer than i matching bits before it.
+        let (mut lo, mut hi) = (
+            start as usize / SUPER_BLOCK_SIZE,
+            end as usize / SUPER_BLOCK_SIZE,
+        );
+        while lo < hi {
+            let m = (lo + hi).div_ceil(2);
+            if super_block_rank(&self.r, m) < i {
+                lo = m;
+            } else {
+                hi = m - 1;
+            }
         }
+        let sb = lo;
+        let remaining = i - super_block_rank(&self.r, sb);
+        let mut k = 0;
+        while k + 1 < WORDS_PER_SUPER_BLOCK && block_rank(&self.r, sb, k + 1) < remaining {
+            k += 1;
+        }
+        let w = sb * WORDS_PER_SUPER_BLOCK + k;
+        let r = (remaining - block_rank(&self.r, sb, k) - 1) as u32;
+        let word = self.r.bv.words()[w];
+        let word = if ones { word } else { !word };
+        (w * WORD_SIZE + select_in_word(word, r)) as u64
     }
     pub(in crate) fn compute_index(&mut self) {
         self.r.to_mut().compute_index();
+        self.samples1 = SelectSupport::compute_samples(&self.r, self.sample_rate, true);
+        self.samples0 = SelectSupport::compute_samples(&self.r, self.sample_rate, false);
     }
     pub(in crate) fn set(&mut self, i: usize) {
         self.r.to_mut().set(i);
     }
+    pub(in crate) fn clear(&mut self, i: usize) {
+        self.r.to_mut().clear(i);
+    }
     pub(in crate) fn rank1(&self, u: u64) -> u64 {
         self.r.rank1(u)
     }
@@ -42,7 +108,29 @@ impl<'bv> SelectSupport<'bv> {
 
 impl<'bv> SelectSupport<'bv> /* Public API */ {
     pub fn new(r: Cow<'bv, RankSupport<'bv>>) -> SelectSupport<'bv> {
-        SelectSupport { r }
+        SelectSupport::new_with_sample_rate(r, DEFAULT_SAMPLE_RATE)
+    }
+    pub fn new_with_sample_rate(
+        r: Cow<'bv, RankSupport<'bv>>,
+        sample_rate: usize,
+    ) -> SelectSupport<'bv> {
+        assert!(sample_rate > 0, "select sample rate must be positive");
+        let samples1 = SelectSupport::compute_samples(&r, sample_rate, true);
+        let samples0 = SelectSupport::compute_samples(&r, sample_rate, false);
+        SelectSupport {
+            r,
+            sample_rate,
+            samples1,
+            samples0,
+        }
+    }
+    /// Builds rank and select support owning `bv`.
+    pub(in crate) fn new_with_bit_vec(bv: BitVec) -> SelectSupport<'static> {
+        let r = RankSupport::new_with_index_computation(Cow::Owned(bv));
+        SelectSupport::new(Cow::Owned(r))
+    }
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
     }
     pub fn dummy_selectn(&self, i: usize) -> Option<u64> {
         let mut select = 0;
@@ -61,19 +149,37 @@ impl<'bv> SelectSupport<'bv> /* Public API */ {
         }
     }
     pub fn select1(&self, i: u64) -> Option<u64> {
+        if i == 0 || i > self.r.count_ones() {
+            None
+        } else {
+            Some(self.sampled_select(i, true))
+        }
+    }
+    pub fn dummy_select0n(&self, i: usize) -> Option<u64> {
         if i == 0 {
             return None;
         }
-        let size = self.r.bv.size;
-        let max_rank = self.r.rank1(self.r.bv.size as u64 - 1);
-        if i > max_rank {
+        let mut seen = 0;
+        for ind in 0..self.r.bv.size {
+            seen += !self.r.bv.get(ind) as usize;
+            if seen == i {
+                return Some(ind as u64);
+            }
+        }
+        None
+    }
+    pub fn select0(&self, i: u64) -> Option<u64> {
+        let zeros = self.r.bv.size as u64 - self.r.count_ones();
+        if i == 0 || i > zeros {
             None
         } else {
-            Some(self.binary_search_select1(0, size, i))
+            Some(self.sampled_select(i, false))
         }
     }
-    pub fn overhead(self) -> usize {
+    pub fn overhead(&self) -> usize {
         self.r.overhead()
+            + self.samples1.overhead()
+            + self.samples0.overhead()
     }
     pub fn save(&self, file_name: &str) -> std::io::Result<()> {
         let serialized = serde_json::to_string(&self)?;
@@ -118,3 +224,104 @@ mod select1_tests {
         }
     }
 }
+
+#[cfg(test)]
+mod sampled_select1_tests {
+    use crate::bit_vec::BitVec;
+    use crate::rank_support::RankSupport;
+    use crate::select_support::SelectSupport;
+    use std::borrow::Cow;
+    #[test]
+    fn sample_rate_tests() {
+        for sample_rate in [1, 3, 64, 512, 4096] {
+            for size in [1, 100, 1000, 5000] {
+                let b = BitVec::new_with_random(size);
+                let r = RankSupport::new(&b);
+                let s = SelectSupport::new_with_sample_rate(Cow::Borrowed(&r), sample_rate);
+                let ones: Vec<u64> = (0..size as u64).filter(|&i| b.get(i as usize)).collect();
+                for (j, pos) in ones.iter().enumerate() {
+                    assert_eq!(s.select1(j as u64 + 1), Some(*pos));
+                }
+                assert_eq!(s.select1(0), None);
+                assert_eq!(s.select1(ones.len() as u64 + 1), None);
+            }
+        }
+    }
+    #[test]
+    fn sparse_tests() {
+        let size = 100_000;
+        let mut b = BitVec::new(size);
+        let positions = [0, 1, 511, 512, 4000, 60_000, 99_999];
+        for p in positions {
+            b.set(p);
+        }
+        let r = RankSupport::new(&b);
+        let s = SelectSupport::new_with_sample_rate(Cow::Borrowed(&r), 2);
+        for (j, pos) in positions.iter().enumerate() {
+            assert_eq!(s.select1(j as u64 + 1), Some(*pos as u64));
+        }
+        assert_eq!(s.select1(positions.len() as u64 + 1), None);
+    }
+    #[test]
+    fn save_load_test() {
+        let b = BitVec::new_with_random(2048);
+        let r = RankSupport::new(&b);
+        let s = SelectSupport::new_with_sample_rate(Cow::Borrowed(&r), 16);
+        s.save("example_select.txt").unwrap();
+        let s2 = SelectSupport::load("example_select.txt".to_owned()).unwrap();
+        assert_eq!(s2.sample_rate(), 16);
+        for j in 1..=b.count_ones() {
+            assert_eq!(s.select1(j), s2.select1(j));
+        }
+    }
+}
+
+#[cfg(test)]
+mod select0_tests {
+    use crate::bit_vec::BitVec;
+    use crate::rank_support::RankSupport;
+    use crate::select_support::SelectSupport;
+    use std::borrow::Cow;
+    #[test]
+    fn small_tests() {
+        for size in (1..=1100).step_by(11) {
+            let b = BitVec::new_with_random(size);
+            let r = RankSupport::new(&b);
+            let s = SelectSupport::new_with_sample_rate(Cow::Borrowed(&r), 7);
+            for j in 0..=b.size + 1 {
+                let dummy_res = SelectSupport::dummy_select0n(&s, j);
+                let smart_res = s.select0(j as u64);
+                assert_eq!(
+                    dummy_res, smart_res,
+                    "<============= Case [size: {}, point: {}] Starts ==============>\n \
+                    BV = {}\n\
+                    Select0 = {:?}\n\
+                    Dummy Select0 = {:?}\n\
+                    <============= Case [size: {}, point: {}] Ends ==============>\n",
+                    size, j, b, smart_res, dummy_res, size, j
+                );
+            }
+        }
+    }
+    #[test]
+    fn dense_tests() {
+        let size = 100_000;
+        let b = BitVec::new_with_words(vec![u64::MAX; size / 64 + 1], size);
+        let r = RankSupport::new(&b);
+        let s = SelectSupport::new(Cow::Borrowed(&r));
+        assert_eq!(s.select0(1), None);
+
+        let positions = [3, 700, 50_000, 99_999];
+        let mut b = BitVec::new(size);
+        for i in (0..size).filter(|i| !positions.contains(i)) {
+            b.set(i);
+        }
+        let r = RankSupport::new(&b);
+        let s = SelectSupport::new_with_sample_rate(Cow::Borrowed(&r), 2);
+        for (j, pos) in positions.iter().enumerate() {
+            assert_eq!(s.select0(j as u64 + 1), Some(*pos as u64));
+            assert_eq!(r.rank0(*pos as u64), j as u64 + 1);
+        }
+        assert_eq!(s.select0(5), None);
+    }
+}
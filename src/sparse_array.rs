@@ -20,6 +20,9 @@ impl<'bv, T> SparseArray<'bv, T> {
     fn set_bv_index(&mut self, i: usize) {
         self.s.set(i);
     }
+    fn clear_bv_index(&mut self, i: usize) {
+        self.s.clear(i);
+    }
 }
 impl<'bv, T> SparseArray<'bv, T> /* Public API */ {
     pub fn new(size: usize) -> SparseArray<'bv, T> {
@@ -34,6 +37,16 @@ impl<'bv, T> SparseArray<'bv, T> /* Public API */ {
         self.set_bv_index(pos);
         self.compute_index();
     }
+    /// Removes the element at index `pos` and returns it, if there is one.
+    pub fn remove(&mut self, pos: usize) -> Option<T> {
+        if !self.s.r.bv.get(pos) {
+            return None;
+        }
+        let rank = self.s.rank1(pos as u64);
+        self.clear_bv_index(pos);
+        self.compute_index();
+        Some(self.v.remove(rank as usize - 1))
+    }
     pub fn get_at_rank(&self, u: usize) -> Option<&T> {
         if let Some(elem_) = self.v.get(u - 1) {
             Some(elem_)
@@ -123,6 +136,23 @@ mod sparse_array_tests {
         }
     }
 
+    #[test]
+    fn remove_tests() {
+        let mut sa: SparseArray<String> = SparseArray::new(200);
+        for pos in [3, 64, 65, 150] {
+            sa.append(pos.to_string(), pos);
+        }
+        assert_eq!(sa.remove(64), Some("64".to_owned()));
+        assert_eq!(sa.remove(64), None);
+        assert_eq!(sa.remove(4), None);
+        assert_eq!(sa.num_elem(), 3);
+        assert_eq!(sa.get_at_index(65), Some(&"65".to_owned()));
+        assert_eq!(sa.get_at_rank(2), Some(&"65".to_owned()));
+        assert_eq!(sa.num_elem_at(150), 3);
+        sa.append("180".to_owned(), 180);
+        assert_eq!(sa.get_at_index(180), Some(&"180".to_owned()));
+    }
+
     #[test]
     fn test_generic_construction() {
         #[derive(PartialEq, Debug, Serialize, Deserialize)]
@@ -157,7 +187,7 @@ mod sparse_array_tests {
         let mut sa: SparseArray<Point> = SparseArray::new(size);
         sa.append(Point { x: 3, y: -4 }, 3);
         sa.save("example_sparse_array.txt")?;
-        let mut sa2: SparseArray<Point> =
+        let sa2: SparseArray<Point> =
             SparseArray::load("example_sparse_array.txt".to_owned()).unwrap();
         let res = sa2.get_at_index(3).unwrap();
         assert_eq!(*res, Point { x: 3, y: -4 });
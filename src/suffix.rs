@@ -0,0 +1,302 @@
+//! Suffix array, Burrows-Wheeler transform and LCP array construction.
+//!
+//! Suffix arrays are built with SA-IS (Nong, Zhang and Chan) in linear time.
+//! Every construction has a `dummy_*` counterpart computed by sorting the
+//! suffixes directly, to check results against.
+
+const EMPTY: usize = usize::MAX;
+
+/// Start (or end, with `end == true`) of each symbol's bucket.
+fn buckets(sizes: &[usize], end: bool) -> Vec<usize> {
+    let mut sum = 0;
+    sizes
+        .iter()
+        .map(|size| {
+            sum += size;
+            if end {
+                sum
+            } else {
+                sum - size
+            }
+        })
+        .collect()
+}
+
+/// Places `lms` (in the given order) at the ends of their buckets, then induces
+/// the L-type suffixes left to right and the S-type suffixes right to left.
+fn induce(s: &[usize], stype: &[bool], sizes: &[usize], lms: &[usize], sa: &mut [usize]) {
+    sa.fill(EMPTY);
+    let mut tails = buckets(sizes, true);
+    for p in lms.iter().rev() {
+        tails[s[*p]] -= 1;
+        sa[tails[s[*p]]] = *p;
+    }
+    let mut heads = buckets(sizes, false);
+    for i in 0..sa.len() {
+        if sa[i] != EMPTY && sa[i] > 0 && !stype[sa[i] - 1] {
+            let j = sa[i] - 1;
+            sa[heads[s[j]]] = j;
+            heads[s[j]] += 1;
+        }
+    }
+    let mut tails = buckets(sizes, true);
+    for i in (0..sa.len()).rev() {
+        if sa[i] != EMPTY && sa[i] > 0 && stype[sa[i] - 1] {
+            let j = sa[i] - 1;
+            tails[s[j]] -= 1;
+            sa[tails[s[j]]] = j;
+        }
+    }
+}
+
+/// SA-IS over `s`, whose symbols are below `sigma` and whose last symbol is a
+/// unique `0` sentinel.
+fn sais(s: &[usize], sigma: usize) -> Vec<usize> {
+    let n = s.len();
+    if n == 1 {
+        return vec![0];
+    }
+    let mut stype = vec![false; n];
+    stype[n - 1] = true;
+    for i in (0..n - 1).rev() {
+        stype[i] = s[i] < s[i + 1] || (s[i] == s[i + 1] && stype[i + 1]);
+    }
+    let is_lms = |i: usize| i > 0 && stype[i] && !stype[i - 1];
+    let mut sizes = vec![0; sigma];
+    for c in s {
+        sizes[*c] += 1;
+    }
+
+    // Sort the LMS substrings by inducing from the LMS positions in text order.
+    let lms: Vec<usize> = (1..n).filter(|i| is_lms(*i)).collect();
+    let mut sa = vec![EMPTY; n];
+    induce(s, &stype, &sizes, &lms, &mut sa);
+
+    // Name the LMS substrings, equal substrings getting equal names.
+    let mut names = vec![EMPTY; n];
+    let mut name = 0;
+    let mut prev = EMPTY;
+    for p in sa.iter().copied().filter(|p| is_lms(*p)) {
+        if prev != EMPTY && !lms_substrings_equal(s, &stype, prev, p, is_lms) {
+            name += 1;
+        }
+        names[p] = name;
+        prev = p;
+    }
+    let reduced: Vec<usize> = lms.iter().map(|p| names[*p]).collect();
+
+    // Sort the LMS suffixes, recursing if the names are not yet unique.
+    let reduced_sa = if name + 1 < reduced.len() {
+        sais(&reduced, name + 1)
+    } else {
+        let mut reduced_sa = vec![0; reduced.len()];
+        for (i, name) in reduced.iter().enumerate() {
+            reduced_sa[*name] = i;
+        }
+        reduced_sa
+    };
+    let sorted_lms: Vec<usize> = reduced_sa.iter().map(|i| lms[*i]).collect();
+    induce(s, &stype, &sizes, &sorted_lms, &mut sa);
+    sa
+}
+
+fn lms_substrings_equal(
+    s: &[usize],
+    stype: &[bool],
+    a: usize,
+    b: usize,
+    is_lms: impl Fn(usize) -> bool,
+) -> bool {
+    let n = s.len();
+    for j in 0.. {
+        if a + j == n || b + j == n {
+            return false;
+        }
+        if s[a + j] != s[b + j] || stype[a + j] != stype[b + j] {
+            return false;
+        }
+        if j > 0 && (is_lms(a + j) || is_lms(b + j)) {
+            return is_lms(a + j) && is_lms(b + j);
+        }
+    }
+    unreachable!()
+}
+
+/// Suffix array of a byte text.
+pub fn suffix_array(text: &[u8]) -> Vec<usize> {
+    suffix_array_int(text)
+}
+
+/// Suffix array of a text over an integer alphabet. Large alphabets are
+/// renamed to `[0, distinct symbols)` first.
+pub fn suffix_array_int<T: Copy + Into<u64>>(text: &[T]) -> Vec<usize> {
+    let values: Vec<u64> = text.iter().map(|c| (*c).into()).collect();
+    let max = values.iter().max().map_or(0, |max| *max);
+    let mut s: Vec<usize> = if max < values.len() as u64 {
+        values.iter().map(|c| *c as usize + 1).collect()
+    } else {
+        let mut alphabet = values.clone();
+        alphabet.sort_unstable();
+        alphabet.dedup();
+        values
+            .iter()
+            .map(|c| alphabet.binary_search(c).unwrap() + 1)
+            .collect()
+    };
+    let sigma = s.iter().max().map_or(0, |max| *max) + 1;
+    s.push(0);
+    let sa = sais(&s, sigma);
+    sa[1..].to_vec()
+}
+
+/// Suffix array computed by sorting the suffixes.
+pub fn dummy_suffix_array<T: Ord>(text: &[T]) -> Vec<usize> {
+    let mut sa: Vec<usize> = (0..text.len()).collect();
+    sa.sort_by(|a, b| text[*a..].cmp(&text[*b..]));
+    sa
+}
+
+/// Burrows-Wheeler transform of `text` followed by an implicit sentinel
+/// smaller than every symbol, given the suffix array of `text`.
+///
+/// The sentinel itself is left out of the returned transform; the second value
+/// is the row it would occupy, which [`inverse_bwt`] needs.
+pub fn bwt<T: Copy>(text: &[T], sa: &[usize]) -> (Vec<T>, usize) {
+    let n = text.len();
+    let mut res = Vec::with_capacity(n);
+    let mut primary = 0;
+    // Row 0 is the sentinel suffix, preceded by the last symbol.
+    if n > 0 {
+        res.push(text[n - 1]);
+    }
+    for (row, pos) in sa.iter().enumerate() {
+        if *pos == 0 {
+            primary = row + 1;
+        } else {
+            res.push(text[pos - 1]);
+        }
+    }
+    (res, primary)
+}
+
+/// Rebuilds the text from its transform and sentinel row, as returned by
+/// [`bwt`].
+pub fn inverse_bwt<T: Copy + Ord>(bwt: &[T], primary: usize) -> Vec<T> {
+    let n = bwt.len();
+    if n == 0 {
+        return vec![];
+    }
+    // Stable sort of the rows by last symbol, the sentinel row first, gives
+    // the inverse of the LF mapping.
+    let mut order: Vec<usize> = (0..=n).filter(|row| *row != primary).collect();
+    let symbol = |row: usize| if row < primary { bwt[row] } else { bwt[row - 1] };
+    order.sort_by_key(|row| symbol(*row));
+    let mut lf = vec![0; n + 1];
+    lf[primary] = 0;
+    for (i, row) in order.iter().enumerate() {
+        lf[*row] = i + 1;
+    }
+    let mut text = vec![bwt[0]; n];
+    let mut row = 0;
+    for i in (0..n).rev() {
+        text[i] = symbol(row);
+        row = lf[row];
+    }
+    text
+}
+
+/// Longest common prefix of each suffix with the previous one in suffix array
+/// order (Kasai et al.), `0` for the first.
+pub fn lcp_array<T: Eq>(text: &[T], sa: &[usize]) -> Vec<usize> {
+    let n = text.len();
+    let mut rank = vec![0; n];
+    for (row, pos) in sa.iter().enumerate() {
+        rank[*pos] = row;
+    }
+    let mut lcp = vec![0; n];
+    let mut h = 0;
+    for i in 0..n {
+        if rank[i] == 0 {
+            h = 0;
+            continue;
+        }
+        let j = sa[rank[i] - 1];
+        while i + h < n && j + h < n && text[i + h] == text[j + h] {
+            h += 1;
+        }
+        lcp[rank[i]] = h;
+        h = h.saturating_sub(1);
+    }
+    lcp
+}
+
+/// LCP array computed by comparing neighbouring suffixes directly.
+pub fn dummy_lcp_array<T: Eq>(text: &[T], sa: &[usize]) -> Vec<usize> {
+    let mut lcp = vec![0; sa.len()];
+    for row in 1..sa.len() {
+        let (a, b) = (&text[sa[row - 1]..], &text[sa[row]..]);
+        lcp[row] = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    }
+    lcp
+}
+
+#[cfg(test)]
+mod suffix_tests {
+    use crate::suffix::*;
+    use rand::Rng;
+
+    fn random_text(n: usize, sigma: u8) -> Vec<u8> {
+        let mut rng = rand::thread_rng();
+        (0..n).map(|_| rng.gen_range(0..sigma)).collect()
+    }
+
+    #[test]
+    fn suffix_array_tests() {
+        for sigma in [1, 2, 4, 255] {
+            for n in [0, 1, 2, 3, 10, 100, 1000] {
+                let text = random_text(n, sigma);
+                assert_eq!(suffix_array(&text), dummy_suffix_array(&text), "{:?}", text);
+            }
+        }
+    }
+    #[test]
+    fn repetitive_tests() {
+        for text in [
+            b"aaaaaaaaaaaaaaaa".to_vec(),
+            b"abababababababab".to_vec(),
+            b"mississippi".to_vec(),
+            b"abracadabraabracadabra".to_vec(),
+            b"\x00\x00\x00\x01\x00\x00".to_vec(),
+        ] {
+            assert_eq!(suffix_array(&text), dummy_suffix_array(&text));
+        }
+    }
+    #[test]
+    fn integer_alphabet_tests() {
+        let mut rng = rand::thread_rng();
+        for sigma in [3, 1000, u32::MAX] {
+            let text: Vec<u32> = (0..500).map(|_| rng.gen_range(0..sigma)).collect();
+            assert_eq!(suffix_array_int(&text), dummy_suffix_array(&text));
+        }
+    }
+    #[test]
+    fn bwt_tests() {
+        let text = b"banana";
+        let sa = suffix_array(text);
+        let (transform, primary) = bwt(text, &sa);
+        assert_eq!((transform.as_slice(), primary), (&b"annbaa"[..], 4));
+        for n in [0, 1, 2, 50, 777] {
+            let text = random_text(n, 4);
+            let (transform, primary) = bwt(&text, &suffix_array(&text));
+            assert_eq!(inverse_bwt(&transform, primary), text);
+        }
+    }
+    #[test]
+    fn lcp_tests() {
+        for sigma in [1, 2, 4, 26] {
+            let text = random_text(600, sigma);
+            let sa = suffix_array(&text);
+            assert_eq!(lcp_array(&text, &sa), dummy_lcp_array(&text, &sa));
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use crate::bit_vec::BitVec;
+use crate::select_support::SelectSupport;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// Wavelet matrix over the integer alphabet `[0, sigma)`.
+///
+/// Level `l` holds bit `l` (most significant first) of every symbol. Before
+/// the next level the symbols are stably reordered so that those with a zero
+/// at level `l` come first, and `zeros[l]` records where the ones start. Unlike
+/// [`WaveletTree`](crate::WaveletTree) no node boundaries are needed, so there
+/// are exactly `⌈log sigma⌉` levels whatever the alphabet.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct WaveletMatrix {
+    levels: Vec<SelectSupport<'static>>,
+    zeros: Vec<usize>,
+    len: usize,
+    sigma: u64,
+}
+
+impl WaveletMatrix {
+    fn depth(&self) -> usize {
+        self.levels.len()
+    }
+    fn bit(&self, c: u64, level: usize) -> bool {
+        (c >> (self.depth() - 1 - level)) & 1 == 1
+    }
+    fn rank1(&self, level: usize, i: usize) -> usize {
+        self.levels[level].r.rank1_exclusive(i) as usize
+    }
+    fn rank0(&self, level: usize, i: usize) -> usize {
+        i - self.rank1(level, i)
+    }
+    /// Maps position `i` of `level` to the next level, following `bit`.
+    fn next(&self, level: usize, i: usize, bit: bool) -> usize {
+        if bit {
+            self.zeros[level] + self.rank1(level, i)
+        } else {
+            self.rank0(level, i)
+        }
+    }
+    /// Number of symbols smaller than `x` in `[l, r)`.
+    fn count_less(&self, l: usize, r: usize, x: u64) -> usize {
+        if 1_u64.checked_shl(self.depth() as u32).is_some_and(|max| x >= max) {
+            return r - l;
+        }
+        let (mut l, mut r) = (l, r);
+        let mut count = 0;
+        for level in 0..self.depth() {
+            let bit = self.bit(x, level);
+            if bit {
+                count += self.rank0(level, r) - self.rank0(level, l);
+            }
+            l = self.next(level, l, bit);
+            r = self.next(level, r, bit);
+        }
+        count
+    }
+}
+
+impl WaveletMatrix /* Public API */ {
+    pub fn new<T: Copy + Into<u64>>(seq: &[T]) -> WaveletMatrix {
+        let mut seq: Vec<u64> = seq.iter().map(|c| (*c).into()).collect();
+        let sigma = seq.iter().max().map_or(1, |max| max + 1);
+        let depth = (u64::BITS - (sigma - 1).leading_zeros()).max(1) as usize;
+        let mut levels = Vec::with_capacity(depth);
+        let mut zeros = Vec::with_capacity(depth);
+        for level in 0..depth {
+            let shift = depth - 1 - level;
+            let mut bv = BitVec::new(seq.len());
+            for (i, c) in seq.iter().enumerate() {
+                if (c >> shift) & 1 == 1 {
+                    bv.set(i);
+                }
+            }
+            zeros.push(seq.len() - bv.count_ones() as usize);
+            levels.push(SelectSupport::new_with_bit_vec(bv));
+            seq.sort_by_key(|c| (c >> shift) & 1);
+        }
+        WaveletMatrix {
+            levels,
+            zeros,
+            len: seq.len(),
+            sigma,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// One more than the largest symbol.
+    pub fn sigma(&self) -> u64 {
+        self.sigma
+    }
+    pub fn access(&self, i: usize) -> Option<u64> {
+        if i >= self.len {
+            return None;
+        }
+        let mut i = i;
+        let mut c = 0;
+        for level in 0..self.depth() {
+            let bit = self.levels[level].r.bv.get(i);
+            c = (c << 1) | bit as u64;
+            i = self.next(level, i, bit);
+        }
+        Some(c)
+    }
+    /// Number of occurrences of `c` in `[0, i)`.
+    pub(in crate) fn rank_exclusive(&self, c: u64, i: usize) -> usize {
+        if c >= self.sigma {
+            return 0;
+        }
+        let (mut start, mut end) = (0, i.min(self.len));
+        for level in 0..self.depth() {
+            let bit = self.bit(c, level);
+            start = self.next(level, start, bit);
+            end = self.next(level, end, bit);
+        }
+        end - start
+    }
+    /// Number of occurrences of `c` in `[0, i]`.
+    pub fn rank(&self, c: u64, i: usize) -> usize {
+        self.rank_exclusive(c, i + 1)
+    }
+    /// Position of the `k`-th occurrence of `c`, counting from one.
+    pub fn select(&self, c: u64, k: usize) -> Option<usize> {
+        if k == 0 || c >= self.sigma {
+            return None;
+        }
+        let (mut start, mut end) = (0, self.len);
+        for level in 0..self.depth() {
+            let bit = self.bit(c, level);
+            start = self.next(level, start, bit);
+            end = self.next(level, end, bit);
+        }
+        if k > end - start {
+            return None;
+        }
+        let mut pos = start + k - 1;
+        for level in (0..self.depth()).rev() {
+            pos = if self.bit(c, level) {
+                self.levels[level].select1((pos - self.zeros[level] + 1) as u64)
+            } else {
+                self.levels[level].select0(pos as u64 + 1)
+            }
+            .unwrap() as usize;
+        }
+        Some(pos)
+    }
+    /// The `k`-th smallest symbol in `[l, r)`, counting from zero.
+    pub fn quantile(&self, l: usize, r: usize, k: usize) -> Option<u64> {
+        let r = r.min(self.len);
+        if l >= r || k >= r - l {
+            return None;
+        }
+        let (mut l, mut r, mut k) = (l, r, k);
+        let mut c = 0;
+        for level in 0..self.depth() {
+            let zeros = self.rank0(level, r) - self.rank0(level, l);
+            let bit = k >= zeros;
+            if bit {
+                k -= zeros;
+            }
+            c = (c << 1) | bit as u64;
+            l = self.next(level, l, bit);
+            r = self.next(level, r, bit);
+        }
+        Some(c)
+    }
+    /// Number of positions in `[l, r)` holding a symbol in `[lo, hi)`.
+    pub fn range_count(&self, l: usize, r: usize, lo: u64, hi: u64) -> usize {
+        let r = r.min(self.len);
+        if l >= r || lo >= hi {
+            return 0;
+        }
+        self.count_less(l, r, hi) - self.count_less(l, r, lo)
+    }
+    /// The `k` most frequent symbols in `[l, r)` with their frequencies, most
+    /// frequent first. Ties are broken towards the smaller symbol.
+    pub fn top_k(&self, l: usize, r: usize, k: usize) -> Vec<(u64, usize)> {
+        let r = r.min(self.len);
+        let mut res = Vec::with_capacity(k);
+        if l >= r {
+            return res;
+        }
+        // Nodes ordered by range size, then by the smallest symbol they cover.
+        let key = |c: u64, level: usize| {
+            Reverse(c.checked_shl((self.depth() - level) as u32).unwrap_or(0))
+        };
+        let mut heap = BinaryHeap::new();
+        heap.push((r - l, key(0, 0), 0, l, r, 0));
+        while let Some((size, _, level, l, r, c)) = heap.pop() {
+            if res.len() == k {
+                break;
+            }
+            if level == self.depth() {
+                res.push((c, size));
+                continue;
+            }
+            for bit in [false, true] {
+                let (nl, nr) = (self.next(level, l, bit), self.next(level, r, bit));
+                if nl < nr {
+                    let nc = (c << 1) | bit as u64;
+                    heap.push((nr - nl, key(nc, level + 1), level + 1, nl, nr, nc));
+                }
+            }
+        }
+        res
+    }
+    /// Largest symbol not greater than `x` in `[l, r)`.
+    pub fn prev_value(&self, l: usize, r: usize, x: u64) -> Option<u64> {
+        let r = r.min(self.len);
+        if l >= r {
+            return None;
+        }
+        match self.count_less(l, r, x.saturating_add(1)) {
+            0 => None,
+            count => self.quantile(l, r, count - 1),
+        }
+    }
+    /// Smallest symbol not less than `x` in `[l, r)`.
+    pub fn next_value(&self, l: usize, r: usize, x: u64) -> Option<u64> {
+        let r = r.min(self.len);
+        if l >= r {
+            return None;
+        }
+        self.quantile(l, r, self.count_less(l, r, x))
+    }
+    pub fn overhead(&self) -> usize {
+        self.levels
+            .iter()
+            .map(|level| std::mem::size_of_val(level.r.bv.words()) + level.overhead())
+            .sum::<usize>()
+            + std::mem::size_of_val(&*self.zeros)
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<WaveletMatrix> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: WaveletMatrix = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod wavelet_matrix_tests {
+    use crate::wavelet_matrix::WaveletMatrix;
+    use rand::Rng;
+    use std::collections::HashMap;
+
+    fn random_sequence(n: usize, sigma: u32) -> Vec<u32> {
+        let mut rng = rand::thread_rng();
+        (0..n).map(|_| rng.gen_range(0..sigma)).collect()
+    }
+
+    #[test]
+    fn access_rank_select_tests() {
+        for (n, sigma) in [(1, 1), (50, 2), (300, 5), (1000, 40), (700, 3000)] {
+            let seq = random_sequence(n, sigma);
+            let wm = WaveletMatrix::new(&seq);
+            for (i, c) in seq.iter().enumerate() {
+                assert_eq!(wm.access(i), Some(*c as u64));
+            }
+            assert_eq!(wm.access(n), None);
+            let mut counts: HashMap<u64, usize> = HashMap::new();
+            for (i, x) in seq.iter().enumerate() {
+                let count = counts.entry(*x as u64).or_default();
+                *count += 1;
+                assert_eq!(wm.select(*x as u64, *count), Some(i));
+                assert_eq!(wm.rank(*x as u64, i), *count, "rank of {} at {}", x, i);
+            }
+            for (c, count) in counts {
+                assert_eq!(wm.select(c, count + 1), None);
+            }
+        }
+    }
+    #[test]
+    fn quantile_range_count_tests() {
+        let seq = random_sequence(300, 50);
+        let wm = WaveletMatrix::new(&seq);
+        for (l, r) in [(0, 300), (10, 20), (100, 299)] {
+            let mut sorted: Vec<u64> = seq[l..r].iter().map(|c| *c as u64).collect();
+            sorted.sort();
+            for (k, c) in sorted.iter().enumerate() {
+                assert_eq!(wm.quantile(l, r, k), Some(*c));
+            }
+            for (lo, hi) in [(0, 50), (0, 1), (10, 30), (49, 100)] {
+                let expected = seq[l..r].iter().filter(|&&c| lo <= c && c < hi).count();
+                assert_eq!(wm.range_count(l, r, lo as u64, hi as u64), expected);
+            }
+        }
+    }
+    #[test]
+    fn top_k_tests() {
+        let seq = random_sequence(500, 30);
+        let wm = WaveletMatrix::new(&seq);
+        for (l, r) in [(0, 500), (17, 90), (499, 500)] {
+            let mut counts: HashMap<u64, usize> = HashMap::new();
+            for c in &seq[l..r] {
+                *counts.entry(*c as u64).or_default() += 1;
+            }
+            let mut expected: Vec<(u64, usize)> = counts.into_iter().collect();
+            expected.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            expected.truncate(5);
+            assert_eq!(wm.top_k(l, r, 5), expected);
+        }
+    }
+    #[test]
+    fn prev_next_value_tests() {
+        let seq = random_sequence(200, 1000);
+        let wm = WaveletMatrix::new(&seq);
+        for (l, r) in [(0, 200), (50, 60)] {
+            for x in (0..1100).step_by(7) {
+                let prev = seq[l..r].iter().filter(|&&c| c as u64 <= x).max();
+                let next = seq[l..r].iter().filter(|&&c| c as u64 >= x).min();
+                assert_eq!(wm.prev_value(l, r, x), prev.map(|c| *c as u64));
+                assert_eq!(wm.next_value(l, r, x), next.map(|c| *c as u64));
+            }
+        }
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let seq = random_sequence(100, 9);
+        let wm = WaveletMatrix::new(&seq);
+        wm.save("example_wavelet_matrix.txt")?;
+        let wm2 = WaveletMatrix::load("example_wavelet_matrix.txt".to_owned())?;
+        for (i, c) in seq.iter().enumerate() {
+            assert_eq!(wm2.access(i), Some(*c as u64));
+        }
+        Ok(())
+    }
+}
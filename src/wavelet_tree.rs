@@ -0,0 +1,304 @@
+use crate::bit_vec::BitVec;
+use crate::select_support::SelectSupport;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// Balanced wavelet tree over the integer alphabet `[0, sigma)`, stored level
+/// by level.
+///
+/// Level `l` holds bit `l` (most significant first) of every symbol, with the
+/// symbols ordered by their first `l` bits. A tree node is therefore a range
+/// of its level, and moving to a child only needs ranks inside that level.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct WaveletTree {
+    levels: Vec<SelectSupport<'static>>,
+    len: usize,
+    sigma: u64,
+}
+
+/// A node range `[start, end)` of a level, together with a query range
+/// `[l, r)` inside it.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    start: usize,
+    end: usize,
+    l: usize,
+    r: usize,
+}
+
+impl WaveletTree {
+    fn depth(&self) -> usize {
+        self.levels.len()
+    }
+    fn bit(&self, c: u64, level: usize) -> bool {
+        (c >> (self.depth() - 1 - level)) & 1 == 1
+    }
+    fn rank1(&self, level: usize, i: usize) -> usize {
+        self.levels[level].r.rank1_exclusive(i) as usize
+    }
+    fn rank0(&self, level: usize, i: usize) -> usize {
+        i - self.rank1(level, i)
+    }
+    /// Moves `node` to its left (`bit == false`) or right child.
+    fn child(&self, level: usize, node: Node, bit: bool) -> Node {
+        let Node { start, end, l, r } = node;
+        let zeros = self.rank0(level, end) - self.rank0(level, start);
+        if bit {
+            let ones_before = self.rank1(level, start);
+            Node {
+                start: start + zeros,
+                end,
+                l: start + zeros + self.rank1(level, l) - ones_before,
+                r: start + zeros + self.rank1(level, r) - ones_before,
+            }
+        } else {
+            let zeros_before = self.rank0(level, start);
+            Node {
+                start,
+                end: start + zeros,
+                l: start + self.rank0(level, l) - zeros_before,
+                r: start + self.rank0(level, r) - zeros_before,
+            }
+        }
+    }
+    fn root(&self, l: usize, r: usize) -> Node {
+        Node {
+            start: 0,
+            end: self.len,
+            l,
+            r,
+        }
+    }
+    /// Number of symbols smaller than `x` in `[l, r)`.
+    fn count_less(&self, l: usize, r: usize, x: u64) -> usize {
+        if 1_u64.checked_shl(self.depth() as u32).is_some_and(|max| x >= max) {
+            return r - l;
+        }
+        let mut node = self.root(l, r);
+        let mut count = 0;
+        for level in 0..self.depth() {
+            let bit = self.bit(x, level);
+            if bit {
+                let left = self.child(level, node, false);
+                count += left.r - left.l;
+            }
+            node = self.child(level, node, bit);
+        }
+        count
+    }
+}
+
+impl WaveletTree /* Public API */ {
+    pub fn new<T: Copy + Into<u64>>(seq: &[T]) -> WaveletTree {
+        let mut seq: Vec<u64> = seq.iter().map(|c| (*c).into()).collect();
+        let sigma = seq.iter().max().map_or(1, |max| max + 1);
+        let depth = (u64::BITS - (sigma - 1).leading_zeros()).max(1) as usize;
+        let mut levels = Vec::with_capacity(depth);
+        for level in 0..depth {
+            let shift = depth - 1 - level;
+            let mut bv = BitVec::new(seq.len());
+            for (i, c) in seq.iter().enumerate() {
+                if (c >> shift) & 1 == 1 {
+                    bv.set(i);
+                }
+            }
+            levels.push(SelectSupport::new_with_bit_vec(bv));
+            seq.sort_by_key(|c| c >> shift);
+        }
+        WaveletTree {
+            levels,
+            len: seq.len(),
+            sigma,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// One more than the largest symbol.
+    pub fn sigma(&self) -> u64 {
+        self.sigma
+    }
+    pub fn access(&self, i: usize) -> Option<u64> {
+        if i >= self.len {
+            return None;
+        }
+        let mut node = self.root(i, i + 1);
+        let mut c = 0;
+        for level in 0..self.depth() {
+            let bit = self.levels[level].r.bv.get(node.l);
+            c = (c << 1) | bit as u64;
+            node = self.child(level, node, bit);
+        }
+        Some(c)
+    }
+    /// Number of occurrences of `c` in `[0, i]`.
+    pub fn rank(&self, c: u64, i: usize) -> usize {
+        if c >= self.sigma {
+            return 0;
+        }
+        let i = i.saturating_add(1).min(self.len);
+        self.range_count(0, i, c, c + 1)
+    }
+    /// Position of the `k`-th occurrence of `c`, counting from one.
+    pub fn select(&self, c: u64, k: usize) -> Option<usize> {
+        if k == 0 || c >= self.sigma {
+            return None;
+        }
+        let mut path = Vec::with_capacity(self.depth());
+        let mut node = self.root(0, self.len);
+        for level in 0..self.depth() {
+            path.push(node);
+            node = self.child(level, node, self.bit(c, level));
+        }
+        if k > node.end - node.start {
+            return None;
+        }
+        // Offset of the answer inside the current node, walking back up.
+        let mut offset = k - 1;
+        for level in (0..self.depth()).rev() {
+            let start = path[level].start;
+            let pos = if self.bit(c, level) {
+                let before = self.rank1(level, start);
+                self.levels[level].select1((before + offset + 1) as u64)
+            } else {
+                let before = self.rank0(level, start);
+                self.levels[level].select0((before + offset + 1) as u64)
+            };
+            offset = pos.unwrap() as usize - start;
+        }
+        Some(offset)
+    }
+    /// The `k`-th smallest symbol in `[l, r)`, counting from zero.
+    pub fn quantile(&self, l: usize, r: usize, k: usize) -> Option<u64> {
+        let r = r.min(self.len);
+        if l >= r || k >= r - l {
+            return None;
+        }
+        let mut node = self.root(l, r);
+        let mut k = k;
+        let mut c = 0;
+        for level in 0..self.depth() {
+            let left = self.child(level, node, false);
+            let zeros = left.r - left.l;
+            if k < zeros {
+                node = left;
+                c <<= 1;
+            } else {
+                k -= zeros;
+                node = self.child(level, node, true);
+                c = (c << 1) | 1;
+            }
+        }
+        Some(c)
+    }
+    /// Number of positions in `[l, r)` holding a symbol in `[lo, hi)`.
+    pub fn range_count(&self, l: usize, r: usize, lo: u64, hi: u64) -> usize {
+        let r = r.min(self.len);
+        if l >= r || lo >= hi {
+            return 0;
+        }
+        self.count_less(l, r, hi) - self.count_less(l, r, lo)
+    }
+    pub fn overhead(&self) -> usize {
+        self.levels
+            .iter()
+            .map(|level| std::mem::size_of_val(level.r.bv.words()) + level.overhead())
+            .sum()
+    }
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string(&self)?;
+        let mut file = File::create(file_name)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+    pub fn load(file_name: String) -> std::io::Result<WaveletTree> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let deserialized: WaveletTree = serde_json::from_str(&contents)?;
+        Ok(deserialized)
+    }
+}
+
+#[cfg(test)]
+mod wavelet_tree_tests {
+    use crate::wavelet_tree::WaveletTree;
+    use rand::Rng;
+
+    fn random_sequence(n: usize, sigma: u32) -> Vec<u32> {
+        let mut rng = rand::thread_rng();
+        (0..n).map(|_| rng.gen_range(0..sigma)).collect()
+    }
+
+    #[test]
+    fn access_rank_select_tests() {
+        for (n, sigma) in [(1, 1), (50, 2), (300, 5), (1000, 40), (700, 300)] {
+            let seq = random_sequence(n, sigma);
+            let wt = WaveletTree::new(&seq);
+            for (i, c) in seq.iter().enumerate() {
+                assert_eq!(wt.access(i), Some(*c as u64));
+            }
+            assert_eq!(wt.access(n), None);
+            for c in 0..sigma as u64 + 1 {
+                let mut count = 0;
+                for (i, x) in seq.iter().enumerate() {
+                    if *x as u64 == c {
+                        count += 1;
+                        assert_eq!(wt.select(c, count), Some(i));
+                    }
+                    assert_eq!(wt.rank(c, i), count, "rank of {} at {}", c, i);
+                }
+                assert_eq!(wt.select(c, count + 1), None);
+                assert_eq!(wt.rank(c, usize::MAX), count);
+            }
+            assert_eq!(wt.rank(u64::MAX, n), 0);
+        }
+    }
+    #[test]
+    fn quantile_tests() {
+        let seq = random_sequence(200, 17);
+        let wt = WaveletTree::new(&seq);
+        for (l, r) in [(0, 200), (5, 6), (13, 150), (199, 200)] {
+            let mut sorted: Vec<u64> = seq[l..r].iter().map(|c| *c as u64).collect();
+            sorted.sort();
+            for (k, c) in sorted.iter().enumerate() {
+                assert_eq!(wt.quantile(l, r, k), Some(*c));
+            }
+            assert_eq!(wt.quantile(l, r, r - l), None);
+        }
+    }
+    #[test]
+    fn range_count_tests() {
+        let seq = random_sequence(300, 50);
+        let wt = WaveletTree::new(&seq);
+        for (l, r) in [(0, 300), (10, 20), (100, 299)] {
+            for (lo, hi) in [(0, 50), (0, 1), (10, 30), (49, 100), (20, 10)] {
+                let expected = seq[l..r].iter().filter(|&&c| lo <= c && c < hi).count();
+                assert_eq!(wt.range_count(l, r, lo as u64, hi as u64), expected);
+            }
+        }
+    }
+    #[test]
+    fn byte_sequence_test() {
+        let text = b"abracadabra";
+        let wt = WaveletTree::new(text);
+        assert_eq!(wt.rank(b'a' as u64, 10), 5);
+        assert_eq!(wt.select(b'c' as u64, 1), Some(4));
+        assert_eq!(wt.quantile(0, 11, 10), Some(b'r' as u64));
+    }
+    #[test]
+    fn save_load_test() -> std::io::Result<()> {
+        let seq = random_sequence(100, 9);
+        let wt = WaveletTree::new(&seq);
+        wt.save("example_wavelet_tree.txt")?;
+        let wt2 = WaveletTree::load("example_wavelet_tree.txt".to_owned())?;
+        for (i, c) in seq.iter().enumerate() {
+            assert_eq!(wt2.access(i), Some(*c as u64));
+        }
+        Ok(())
+    }
+}